@@ -0,0 +1,201 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{Fields, Generics, Ident, Variant, Visibility};
+
+use crate::{
+    hidden::{is_hidden, Hidden},
+    structs::Layout,
+};
+
+/// Implement `is_*`, `as_*` and `into_*` methods on the generated enum for each of `variants`, named in snake case.
+///
+/// `as_*` and `into_*` return the variant's fields, as a tuple if there's more than one.
+/// They're left out for variants without any (visible) fields, and `hidden` fields are never returned.
+pub fn implement(
+    name: &Ident,
+    vis: &Visibility,
+    generics: &Generics,
+    hidden: &[Hidden],
+    variants: &[Variant],
+    layout: Layout<'_>,
+) -> TokenStream {
+    if variants.is_empty() {
+        return quote!();
+    }
+    let methods = variants
+        .iter()
+        .map(|variant| method(vis, hidden, variant, layout));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            #(#methods)*
+        }
+    }
+}
+
+fn method(
+    vis: &Visibility,
+    hidden: &[Hidden],
+    variant: &Variant,
+    layout: Layout<'_>,
+) -> TokenStream {
+    let ident = &variant.ident;
+    let snake = crate::case::snake_case(ident);
+    let (is, as_ref, into) = (
+        format_ident!("is_{}", snake),
+        format_ident!("as_{}", snake),
+        format_ident!("into_{}", snake),
+    );
+    let is_docs = format!("Whether this is `{ident}`.");
+    let any = match &variant.fields {
+        Fields::Named(_) => layout.path(ident, quote!({ .. })),
+        Fields::Unnamed(_) => layout.path(ident, quote!((..))),
+        Fields::Unit => layout.path(ident, quote!()),
+    };
+    let is = quote! {
+        #[doc = #is_docs]
+        #vis fn #is(&self) -> bool {
+            #[allow(unreachable_patterns)]
+            match self {
+                #any => true,
+                _ => false,
+            }
+        }
+    };
+
+    let fields = variant
+        .fields
+        .iter()
+        .enumerate()
+        .filter(|(_, field)| !is_hidden(field, hidden))
+        .map(|(index, field)| {
+            let binding = match &field.ident {
+                Some(ident) => ident.clone(),
+                None => format_ident!("_{}", index),
+            };
+            (binding, &field.ty)
+        })
+        .collect::<Vec<_>>();
+    if fields.is_empty() {
+        return is;
+    }
+    let bindings = fields
+        .iter()
+        .map(|(binding, _)| binding)
+        .collect::<Vec<_>>();
+    // Hidden fields are always last, so they're skipped by the `..`
+    let pattern = match &variant.fields {
+        Fields::Named(_) => layout.path(ident, quote!({ #(#bindings,)* .. })),
+        _ => layout.path(ident, quote!((#(#bindings,)* ..))),
+    };
+    let (values, refs, owned) = match &fields[..] {
+        [(binding, ty)] => (quote!(#binding), quote!(&#ty), quote!(#ty)),
+        _ => {
+            let types = fields.iter().map(|(_, ty)| ty).collect::<Vec<_>>();
+            (
+                quote!((#(#bindings),*)),
+                quote!((#(&#types),*)),
+                quote!((#(#types),*)),
+            )
+        }
+    };
+    let as_docs = format!("The fields of `{ident}`, if this is one.");
+    let into_docs = format!("Convert into the fields of `{ident}`, if this is one.");
+    quote! {
+        #is
+
+        #[doc = #as_docs]
+        #vis fn #as_ref(&self) -> ::core::option::Option<#refs> {
+            #[allow(unreachable_patterns)]
+            match self {
+                #pattern => ::core::option::Option::Some(#values),
+                _ => ::core::option::Option::None,
+            }
+        }
+
+        #[doc = #into_docs]
+        #vis fn #into(self) -> ::core::option::Option<#owned> {
+            #[allow(unreachable_patterns)]
+            match self {
+                #pattern => ::core::option::Option::Some(#values),
+                _ => ::core::option::Option::None,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::Config, ident};
+    use pretty_assertions::assert_eq;
+    use syn::parse_quote;
+
+    #[test]
+    fn variant_accessors() {
+        let config = Config {
+            location: true,
+            ..Default::default()
+        };
+        let hidden = Hidden::all(&config, &ident("ShaveYaksError"));
+        let variant: Variant = parse_quote!(NotEnoughBuckets {
+            got: usize,
+            required: usize,
+            __location: __ShaveYaksErrorLocation
+        });
+        let expected = quote! {
+            #[doc = "Whether this is `NotEnoughBuckets`."]
+            pub fn is_not_enough_buckets(&self) -> bool {
+                #[allow(unreachable_patterns)]
+                match self {
+                    Self::NotEnoughBuckets { .. } => true,
+                    _ => false,
+                }
+            }
+
+            #[doc = "The fields of `NotEnoughBuckets`, if this is one."]
+            pub fn as_not_enough_buckets(&self) -> ::core::option::Option<(&usize, &usize)> {
+                #[allow(unreachable_patterns)]
+                match self {
+                    Self::NotEnoughBuckets { got, required, .. } => ::core::option::Option::Some((got, required)),
+                    _ => ::core::option::Option::None,
+                }
+            }
+
+            #[doc = "Convert into the fields of `NotEnoughBuckets`, if this is one."]
+            pub fn into_not_enough_buckets(self) -> ::core::option::Option<(usize, usize)> {
+                #[allow(unreachable_patterns)]
+                match self {
+                    Self::NotEnoughBuckets { got, required, .. } => ::core::option::Option::Some((got, required)),
+                    _ => ::core::option::Option::None,
+                }
+            }
+        };
+        assert_eq!(
+            expected.to_string(),
+            method(&parse_quote!(pub), &hidden, &variant, Layout::Inline).to_string()
+        );
+
+        let variant: Variant = parse_quote!(Timeout(__ShaveYaksErrorLocation));
+        let expected = quote! {
+            #[doc = "Whether this is `Timeout`."]
+            fn is_timeout(&self) -> bool {
+                #[allow(unreachable_patterns)]
+                match self {
+                    Self::Timeout(shave_yaks_error::Timeout(..)) => true,
+                    _ => false,
+                }
+            }
+        };
+        assert_eq!(
+            expected.to_string(),
+            method(
+                &Visibility::Inherited,
+                &hidden,
+                &variant,
+                Layout::Wrapped(&ident("shave_yaks_error"))
+            )
+            .to_string()
+        );
+    }
+}
@@ -0,0 +1,81 @@
+use syn::Ident;
+
+/// Split an identifier like `NotEnoughHTTPBuckets` into its words, `["Not", "Enough", "HTTP", "Buckets"]`.
+pub fn words(ident: &Ident) -> Vec<String> {
+    let ident = ident.to_string();
+    let ident = ident.strip_prefix("r#").unwrap_or(&ident);
+    let chars = ident.chars().collect::<Vec<_>>();
+    let mut words = Vec::new();
+    let mut current = String::new();
+    for (index, c) in chars.iter().copied().enumerate() {
+        if c == '_' {
+            words.extend((!current.is_empty()).then(|| std::mem::take(&mut current)));
+            continue;
+        }
+        let previous = index.checked_sub(1).map(|it| chars[it]);
+        let next = chars.get(index + 1).copied();
+        let boundary = c.is_uppercase()
+            && match previous {
+                Some(previous) if previous.is_lowercase() || previous.is_ascii_digit() => true,
+                // The end of an acronym, like the `B` in `HTTPBuckets`
+                Some(previous) if previous.is_uppercase() => next.is_some_and(char::is_lowercase),
+                _ => false,
+            };
+        if boundary && !current.is_empty() {
+            words.push(std::mem::take(&mut current))
+        }
+        current.push(c)
+    }
+    words.extend((!current.is_empty()).then_some(current));
+    words
+}
+
+/// `NotEnoughBuckets` to `not enough buckets`.
+pub fn lower_words(ident: &Ident) -> String {
+    words(ident)
+        .iter()
+        .map(|it| it.to_lowercase())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// `NotEnoughBuckets` to `not_enough_buckets`.
+pub fn snake_case(ident: &Ident) -> String {
+    words(ident)
+        .iter()
+        .map(|it| it.to_lowercase())
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ident;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn split_words() {
+        for (input, expected) in [
+            ("NotEnoughBuckets", &["Not", "Enough", "Buckets"][..]),
+            ("HTTPFailed", &["HTTP", "Failed"]),
+            ("IoError", &["Io", "Error"]),
+            ("Utf8Error", &["Utf8", "Error"]),
+            ("Level2Cache", &["Level2", "Cache"]),
+            ("ABC", &["ABC"]),
+            ("snake_case", &["snake", "case"]),
+            ("X", &["X"]),
+        ] {
+            assert_eq!(expected, words(&ident(input)))
+        }
+        assert_eq!(
+            "not enough http buckets",
+            lower_words(&ident("NotEnoughHTTPBuckets"))
+        );
+        assert_eq!(
+            "not_enough_http_buckets",
+            snake_case(&ident("NotEnoughHTTPBuckets"))
+        );
+        assert_eq!("utf8_error", snake_case(&ident("Utf8Error")))
+    }
+}
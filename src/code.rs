@@ -0,0 +1,231 @@
+use proc_macro2::{Span, TokenStream};
+use proc_macro_error::emit_error;
+use quote::{quote, ToTokens};
+use syn::{parse_quote, Expr, Fields, Generics, Ident, Meta, Variant, Visibility};
+
+use crate::{
+    config::Config,
+    hidden::{is_hidden, Hidden},
+    structs::Layout,
+};
+
+pub fn is_code_attr(attr: &syn::Attribute) -> bool {
+    attr.path().is_ident("code")
+}
+
+/// Take each variant's code from its `#[code = ..]` attribute or discriminant, removing them.
+fn take_code(variant: &mut Variant) -> Option<Expr> {
+    if let Some(position) = variant.attrs.iter().position(is_code_attr) {
+        return match variant.attrs.remove(position).meta {
+            Meta::NameValue(it) => Some(it.value),
+            other => {
+                emit_error!(other, "expected a code like `#[code = -1]`");
+                None
+            }
+        };
+    }
+    if let Some((_, discriminant)) = variant.discriminant.take() {
+        return Some(discriminant);
+    }
+    emit_error!(
+        variant.ident,
+        "`{}` has no code - add one like `err!(#[code = -1] {})` or `err!({} = -1)`",
+        variant.ident,
+        variant.ident,
+        variant.ident
+    );
+    None
+}
+
+/// `code` as written, ignoring spacing - which differs for a negative literal in an attribute.
+fn written(code: &Expr) -> String {
+    code.to_token_stream().to_string().replace(' ', "")
+}
+
+/// Implement `code()` and `from_code(..)` on the generated enum, adding an `Unknown(i32)` variant if there isn't one already.
+///
+/// Codes are compared as written to report duplicates, so a constant and its value aren't caught.
+pub fn implement(
+    name: &Ident,
+    vis: &Visibility,
+    generics: &Generics,
+    hidden: &[Hidden],
+    variants: &mut Vec<Variant>,
+    config: &Config,
+    layout: Layout<'_>,
+) -> TokenStream {
+    let unknown = Ident::new("Unknown", Span::call_site());
+    if let Some(declared) = variants.iter().find(|it| it.ident == unknown) {
+        let visible = declared.fields.iter().filter(|it| !is_hidden(it, hidden));
+        if !matches!(declared.fields, Fields::Unnamed(_)) || visible.count() != 1 {
+            emit_error!(
+                declared.ident,
+                "`{}` holds the codes without a variant, so it must be declared like `{}(i32)`",
+                unknown,
+                unknown
+            )
+        }
+    }
+    let codes = variants
+        .iter_mut()
+        .filter(|it| it.ident != unknown)
+        .filter_map(|variant| {
+            let code = take_code(variant)?;
+            let variant: &Variant = variant;
+            Some((code, variant))
+        })
+        .collect::<Vec<_>>();
+    for (index, (code, variant)) in codes.iter().enumerate() {
+        if let Some((_, first)) = codes[..index]
+            .iter()
+            .find(|(it, _)| written(it) == written(code))
+        {
+            emit_error!(
+                code,
+                "`{}` has the same code as `{}` - each code can only be used by one variant",
+                variant.ident,
+                first.ident
+            )
+        }
+    }
+
+    // Only variants without (visible) fields can be recreated from their code
+    let values = hidden
+        .iter()
+        .map(|it| it.value(Span::call_site()))
+        .collect::<Vec<_>>();
+    let constructions = codes.iter().map(|(code, variant)| {
+        let ident = &variant.ident;
+        let fieldless = variant.fields.iter().all(|it| is_hidden(it, hidden));
+        let construction = match (&variant.fields, fieldless) {
+            (Fields::Named(_), true) => {
+                let names = hidden.iter().map(|it| &it.ident);
+                layout.path(ident, quote!({ #(#names: #values),* }))
+            }
+            (Fields::Unit, _) => layout.path(ident, quote!()),
+            _ => layout.path(&unknown, quote!((code #(, #values)*))),
+        };
+        quote!(code if code == (#code) => ::core::option::Option::Some(#construction))
+    });
+    let arms = codes.iter().map(|(code, variant)| {
+        let ident = &variant.ident;
+        let pattern = match &variant.fields {
+            Fields::Named(_) => layout.path(ident, quote!({ .. })),
+            Fields::Unnamed(_) => layout.path(ident, quote!((..))),
+            Fields::Unit => layout.path(ident, quote!()),
+        };
+        quote!(#pattern => #code)
+    });
+    let unknown_pattern = layout.path(&unknown, quote!((code, ..)));
+    let code = quote! {
+        /// The code for this error.
+        #vis fn code(&self) -> i32 {
+            match self {
+                #(#arms,)*
+                #unknown_pattern => *code,
+            }
+        }
+    };
+    let track_caller = match hidden.is_empty() {
+        true => quote!(),
+        false => quote!(#[track_caller]),
+    };
+    let from_code = quote! {
+        /// The error for a `code`, or `None` if no variant has it.
+        ///
+        /// Variants with fields can't be recreated, so are `Unknown` instead.
+        #track_caller
+        #vis fn from_code(code: i32) -> ::core::option::Option<Self> {
+            match code {
+                #(#constructions,)*
+                _ => ::core::option::Option::None,
+            }
+        }
+    };
+
+    if !variants.iter().any(|it| it.ident == unknown) {
+        let types = hidden.iter().map(|it| &it.ty);
+        let mut variant: Variant = parse_quote!(#unknown(i32 #(, #types)*));
+        if config.display {
+            variant
+                .attrs
+                .push(parse_quote!(#[display("unknown error code {0}")]))
+        } else if config.derives_error() {
+            variant
+                .attrs
+                .push(parse_quote!(#[error("unknown error code {0}")]))
+        }
+        variants.push(variant)
+    }
+
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            #code
+            #from_code
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ident;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn implement_codes() {
+        let mut variants: Vec<Variant> = vec![
+            parse_quote!(
+                #[code = -22]
+                InvalidArg
+            ),
+            parse_quote!(Busy = libc::EBUSY),
+            parse_quote!(
+                #[code = -1]
+                Failed { call: &'static str }
+            ),
+        ];
+        let implemented = implement(
+            &ident("FooError"),
+            &Visibility::Inherited,
+            &Generics::default(),
+            &[],
+            &mut variants,
+            &Config::default(),
+            Layout::Inline,
+        );
+        let expected = quote! {
+            impl FooError {
+                /// The code for this error.
+                fn code(&self) -> i32 {
+                    match self {
+                        Self::InvalidArg => -22,
+                        Self::Busy => libc::EBUSY,
+                        Self::Failed { .. } => -1,
+                        Self::Unknown(code, ..) => *code,
+                    }
+                }
+                /// The error for a `code`, or `None` if no variant has it.
+                ///
+                /// Variants with fields can't be recreated, so are `Unknown` instead.
+                fn from_code(code: i32) -> ::core::option::Option<Self> {
+                    match code {
+                        code if code == (-22) => ::core::option::Option::Some(Self::InvalidArg),
+                        code if code == (libc::EBUSY) => ::core::option::Option::Some(Self::Busy),
+                        code if code == (-1) => ::core::option::Option::Some(Self::Unknown(code)),
+                        _ => ::core::option::Option::None,
+                    }
+                }
+            }
+        };
+        assert_eq!(expected.to_string(), implemented.to_string());
+        let expected: Vec<Variant> = vec![
+            parse_quote!(InvalidArg),
+            parse_quote!(Busy),
+            parse_quote!(Failed { call: &'static str }),
+            parse_quote!(Unknown(i32)),
+        ];
+        assert_eq!(expected, variants);
+    }
+}
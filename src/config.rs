@@ -1,30 +1,63 @@
-use quote::ToTokens;
+use proc_macro2::{Span, TokenStream};
+use quote::{quote, ToTokens};
 use syn::{
     meta::ParseNestedMeta,
     parenthesized,
     parse::{Parse, ParseStream, Parser},
     punctuated::Punctuated,
-    Attribute, Path, Token, Visibility,
+    token, Attribute, Ident, Path, Token, Visibility,
 };
 
-#[derive(Debug, Default, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct Config {
     pub derives: Option<Vec<Path>>,
     pub attributes: Option<Vec<Attribute>>,
     pub visibility: Option<Visibility>,
+    pub error: Option<Ident>,
+    pub aliases: Option<Vec<Path>>,
+    pub display: bool,
+    pub default_display: bool,
+    pub source_by_type: bool,
+    pub parent: Option<Path>,
+    pub location: bool,
+    pub backtrace: bool,
+    /// Whether to provide the backtrace through `Error::provide`, which is nightly-only.
+    pub provide: bool,
+    pub code: bool,
+    /// Whether to add the variants to an enum marked `#[errgo::extendable]`, rather than generating one.
+    pub extend: bool,
+    /// Whether to generate `is_*`, `as_*` and `into_*` methods for each variant.
+    pub accessors: bool,
+    /// Whether to generate a fieldless `{Error}Kind` enum, and a `kind()` method returning it.
+    pub kind: bool,
+    /// Whether each variant wraps a struct of the same name, which holds its fields.
+    pub variant_structs: bool,
+    /// The module the enum is defined in by `errgo::collect!`, rather than alongside the function.
+    pub define_in: Option<Path>,
 }
 
 impl Parse for Config {
     fn parse(input: ParseStream) -> syn::Result<Self> {
         let mut config = Self::default();
         if !input.is_empty() {
-            syn::meta::parser(|stage| config.parse_stage(stage)).parse2(input.parse()?)?;
+            config.parse_more(input.parse()?)?;
         }
         Ok(config)
     }
 }
 
 impl Config {
+    /// Parse further arguments on top of this config, e.g for a method in an `impl` block.
+    pub fn parse_more(&mut self, tokens: TokenStream) -> syn::Result<()> {
+        syn::meta::parser(|stage| self.parse_stage(stage)).parse2(tokens)
+    }
+    /// Whether this derives thiserror's `Error`, so variants need an `#[error(..)]` message.
+    pub fn derives_error(&self) -> bool {
+        self.derives
+            .iter()
+            .flatten()
+            .any(|it| it.segments.last().is_some_and(|it| it.ident == "Error"))
+    }
     fn parse_stage(&mut self, stage: ParseNestedMeta) -> syn::Result<()> {
         if stage.path.is_ident("derive") {
             let content;
@@ -66,9 +99,56 @@ impl Config {
                 return Err(stage.error("`visibility` specified more than once"));
             }
             self.visibility = Some(content.parse()?);
+        } else if stage.path.is_ident("error") {
+            if self.error.is_some() {
+                return Err(stage.error("`error` specified more than once"));
+            }
+            self.error = Some(stage.value()?.parse()?);
+        } else if stage.path.is_ident("parent") {
+            if self.parent.is_some() {
+                return Err(stage.error("`parent` specified more than once"));
+            }
+            self.parent = Some(stage.value()?.parse()?);
+        } else if stage.path.is_ident("define_in") {
+            if self.define_in.is_some() {
+                return Err(stage.error("`define_in` specified more than once"));
+            }
+            self.define_in = Some(stage.value()?.parse()?);
+        } else if stage.path.is_ident("alias") {
+            let alias = stage.value()?.parse()?;
+            self.aliases.get_or_insert_with(Vec::new).push(alias);
+        } else if stage.path.is_ident("display") {
+            self.display = true;
+        } else if stage.path.is_ident("default_display") {
+            self.default_display = true;
+        } else if stage.path.is_ident("location") {
+            self.location = true;
+        } else if stage.path.is_ident("backtrace") {
+            self.backtrace = true;
+            if stage.input.peek(token::Paren) {
+                stage.parse_nested_meta(|nested| match nested.path.is_ident("provide") {
+                    true => {
+                        self.provide = true;
+                        Ok(())
+                    }
+                    false => Err(nested.error("expected `provide`")),
+                })?;
+            }
+        } else if stage.path.is_ident("code") {
+            self.code = true;
+        } else if stage.path.is_ident("extend") {
+            self.extend = true;
+        } else if stage.path.is_ident("accessors") {
+            self.accessors = true;
+        } else if stage.path.is_ident("kind") {
+            self.kind = true;
+        } else if stage.path.is_ident("variant_structs") {
+            self.variant_structs = true;
+        } else if stage.path.is_ident("source_by_type") {
+            self.source_by_type = true;
         } else {
             return Err(stage.error(format!(
-                "unexpected argument `{}`, expected `derive` or `attributes` or `visibility`",
+                "unexpected argument `{}`, expected `derive`, `attributes`, `visibility`, `error`, `alias`, `parent`, `display`, `default_display`, `source_by_type`, `location`, `backtrace`, `code`, `extend`, `accessors`, `kind`, `variant_structs` or `define_in`",
                 stage.path.to_token_stream()
             )));
         }
@@ -76,12 +156,79 @@ impl Config {
     }
 }
 
+/// Write the config back out as arguments, to pass it on to another macro.
+impl ToTokens for Config {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let Self {
+            derives,
+            attributes,
+            visibility,
+            error,
+            aliases,
+            display,
+            default_display,
+            source_by_type,
+            parent,
+            location,
+            backtrace,
+            provide,
+            code,
+            extend,
+            accessors,
+            kind,
+            variant_structs,
+            define_in,
+        } = self;
+        let mut args = Vec::new();
+        if let Some(derives) = derives {
+            args.push(quote!(derive(#(#derives),*)))
+        }
+        if let Some(attributes) = attributes {
+            args.push(quote!(attributes(#(#attributes)*)))
+        }
+        if let Some(visibility) = visibility {
+            args.push(quote!(visibility(#visibility)))
+        }
+        if let Some(error) = error {
+            args.push(quote!(error = #error))
+        }
+        for alias in aliases.iter().flatten() {
+            args.push(quote!(alias = #alias))
+        }
+        if let Some(parent) = parent {
+            args.push(quote!(parent = #parent))
+        }
+        if let Some(define_in) = define_in {
+            args.push(quote!(define_in = #define_in))
+        }
+        for (flag, name) in [
+            (display, "display"),
+            (default_display, "default_display"),
+            (source_by_type, "source_by_type"),
+            (location, "location"),
+            (code, "code"),
+            (extend, "extend"),
+            (accessors, "accessors"),
+            (kind, "kind"),
+            (variant_structs, "variant_structs"),
+        ] {
+            if *flag {
+                args.push(Ident::new(name, Span::call_site()).into_token_stream())
+            }
+        }
+        match (backtrace, provide) {
+            (true, true) => args.push(quote!(backtrace(provide))),
+            (true, false) => args.push(quote!(backtrace)),
+            (false, _) => {}
+        }
+        tokens.extend(quote!(#(#args),*))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::{path, test_utils::test_parse};
-    use proc_macro2::TokenStream;
-    use quote::quote;
 
     fn attributes(tokens: TokenStream) -> Vec<Attribute> {
         Attribute::parse_outer
@@ -97,8 +244,7 @@ mod tests {
             },
             Config {
                 derives: Some(vec![path(["Hello"]), path(["path", "to", "Goodbye"])]),
-                attributes: None,
-                visibility: None,
+                ..Default::default()
             },
         );
     }
@@ -115,7 +261,7 @@ mod tests {
                     #[error("foo")]
                     #[repr(u8)]
                 })),
-                visibility: None,
+                ..Default::default()
             },
         );
     }
@@ -123,6 +269,84 @@ mod tests {
     #[test]
     fn parse_visibility() {}
 
+    #[test]
+    fn parse_error_and_aliases() {
+        test_parse(
+            quote! {
+                error = MyError,
+                alias = crate::Result,
+                alias = Fallible,
+                parent = crate::Error,
+                define_in = errors
+            },
+            Config {
+                error: Some(crate::ident("MyError")),
+                aliases: Some(vec![path(["crate", "Result"]), path(["Fallible"])]),
+                parent: Some(path(["crate", "Error"])),
+                define_in: Some(path(["errors"])),
+                ..Default::default()
+            },
+        );
+    }
+
+    #[test]
+    fn parse_flags() {
+        test_parse(
+            quote!(
+                display,
+                default_display,
+                source_by_type,
+                location,
+                backtrace(provide),
+                code,
+                extend,
+                accessors,
+                kind,
+                variant_structs
+            ),
+            Config {
+                code: true,
+                extend: true,
+                accessors: true,
+                kind: true,
+                variant_structs: true,
+                location: true,
+                backtrace: true,
+                provide: true,
+                display: true,
+                default_display: true,
+                source_by_type: true,
+                ..Default::default()
+            },
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        for tokens in [
+            quote!(),
+            quote! {
+                derive(Debug, thiserror::Error),
+                attributes(#[error("foo")], #[repr(u8)]),
+                visibility(pub(crate)),
+                error = MyError,
+                alias = crate::Result,
+                alias = Fallible,
+                parent = crate::Error,
+                define_in = crate::errors,
+                display,
+                backtrace(provide),
+                code,
+                kind,
+                variant_structs
+            },
+            quote!(visibility(), backtrace, extend, accessors),
+        ] {
+            let config = syn::parse2::<Config>(tokens).unwrap();
+            test_parse(config.to_token_stream(), config);
+        }
+    }
+
     #[test]
     fn parse_all() {
         test_parse(
@@ -136,7 +360,7 @@ mod tests {
                     #[error("foo")]
                     #[repr(u8)]
                 })),
-                visibility: None,
+                ..Default::default()
             },
         );
     }
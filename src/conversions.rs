@@ -0,0 +1,65 @@
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::{Generics, Ident, Type};
+
+use crate::{hidden::Hidden, structs::Layout};
+
+/// Implement `From<Type>` for the generated enum, for each variant which wraps a foreign error with `try_err!`.
+pub fn implement(
+    name: &Ident,
+    generics: &Generics,
+    hidden: &[Hidden],
+    conversions: &[(Ident, Type)],
+    layout: Layout<'_>,
+) -> TokenStream {
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    // So that hidden fields are created where `?` is used
+    let track_caller = match hidden.is_empty() {
+        true => quote!(),
+        false => quote!(#[track_caller]),
+    };
+    let values = hidden
+        .iter()
+        .map(|it| it.value(Span::call_site()))
+        .collect::<Vec<_>>();
+    let impls = conversions.iter().map(|(variant, ty)| {
+        let construction = layout.path(variant, quote!((source, #(#values),*)));
+        quote! {
+            impl #impl_generics ::core::convert::From<#ty> for #name #ty_generics #where_clause {
+                #track_caller
+                fn from(source: #ty) -> Self {
+                    #construction
+                }
+            }
+        }
+    });
+    quote!(#(#impls)*)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ident;
+    use pretty_assertions::assert_eq;
+    use syn::parse_quote;
+
+    #[test]
+    fn implement_from() {
+        let generics: Generics = parse_quote!(<'a>);
+        let implemented = implement(
+            &ident("FooError"),
+            &generics,
+            &[],
+            &[(ident("Io"), parse_quote!(io::Error))],
+            Layout::Inline,
+        );
+        let expected = quote! {
+            impl<'a> ::core::convert::From<io::Error> for FooError<'a> {
+                fn from(source: io::Error) -> Self {
+                    Self::Io(source,)
+                }
+            }
+        };
+        assert_eq!(expected.to_string(), implemented.to_string());
+    }
+}
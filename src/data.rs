@@ -1,9 +1,11 @@
+use quote::{format_ident, ToTokens};
 use syn::{
     braced, parenthesized, parse,
     parse::{Parse, ParseStream},
+    parse_quote,
     punctuated::Punctuated,
     token, Attribute, Expr, ExprCall, ExprPath, ExprStruct, Field, FieldValue, Fields, FieldsNamed,
-    FieldsUnnamed, Ident, Path, PathSegment, Token, Type, Variant, Visibility,
+    FieldsUnnamed, Ident, LitStr, Path, PathSegment, Token, Type, Variant, Visibility,
 };
 
 #[derive(Debug, Clone, PartialEq)]
@@ -12,12 +14,215 @@ pub struct VariantWithValue {
     pub ident: Ident,
     pub fields: MultipleFieldsWithValues,
     pub discriminant: Option<(Token![=], Expr)>,
+    /// A trailing `, "format string", args..`, which is sugar for `#[display(..)]`.
+    pub message: Option<Punctuated<Expr, Token![,]>>,
 }
 
 impl VariantWithValue {
     pub fn into_syn_variant(self) -> syn::Variant {
         self.into()
     }
+    /// Whether all the fields have types, else they must be found elsewhere with [`Self::fill_types`].
+    pub fn is_typed(&self) -> bool {
+        self.fields.is_typed()
+    }
+    /// Take the types of any untyped fields from a `declaration` of this variant elsewhere.
+    pub fn fill_types(&mut self, declaration: &Variant) -> syn::Result<()> {
+        match (&mut self.fields, &declaration.fields) {
+            (MultipleFieldsWithValues::Named(fields), Fields::Named(declared)) => {
+                for field in fields.fields.iter_mut().filter(|it| it.ty.is_none()) {
+                    let Some(declared) = declared
+                        .named
+                        .iter()
+                        .find(|it| it.ident.as_ref() == Some(&field.ident))
+                    else {
+                        return Err(syn::Error::new_spanned(
+                            &field.ident,
+                            format!("no field `{}` on `{}`", field.ident, self.ident),
+                        ));
+                    };
+                    field.ty = Some(declared.ty.clone());
+                    field.eq_token = Some(Default::default());
+                }
+            }
+            (MultipleFieldsWithValues::Unnamed(fields), Fields::Unnamed(declared)) => {
+                for (index, field) in fields.fields.iter_mut().enumerate() {
+                    if field.ty.is_some() {
+                        continue;
+                    }
+                    let Some(declared) = declared.unnamed.iter().nth(index) else {
+                        return Err(syn::Error::new_spanned(
+                            &field.expr,
+                            format!(
+                                "`{}` only has {} field(s)",
+                                self.ident,
+                                declared.unnamed.len()
+                            ),
+                        ));
+                    };
+                    field.ty = Some(declared.ty.clone());
+                    field.eq_token = Some(Default::default());
+                }
+            }
+            (MultipleFieldsWithValues::Unit, _) => {}
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &self.ident,
+                    format!("`{}` is declared with different kind of fields", self.ident),
+                ))
+            }
+        }
+        Ok(())
+    }
+    /// Take the types of fields written `ident = expr` or just `ident` from `params`, the function's parameters.
+    ///
+    /// The expression must be one of the parameters, so its type can be found without type checking -
+    /// unless the variant is `declared` with types elsewhere, when other fields are left for [`Self::fill_types`].
+    pub fn infer_types(&mut self, params: &[(Ident, Type)], declared: bool) -> syn::Result<()> {
+        let MultipleFieldsWithValues::Named(fields) = &mut self.fields else {
+            return Ok(());
+        };
+        for field in fields
+            .fields
+            .iter_mut()
+            .filter(|it| it.colon_token.is_none())
+        {
+            let Some((param, ty)) = expr_ident(&field.expr)
+                .and_then(|param| params.iter().find(|(ident, _)| ident == param))
+            else {
+                if declared {
+                    continue;
+                }
+                return Err(syn::Error::new_spanned(
+                    &field.expr,
+                    format!(
+                        "can't infer the type of `{}` - only a parameter of the function can be used without a type, add one like `{}: Type = ..`",
+                        field.ident, field.ident
+                    ),
+                ));
+            };
+            if let Some(reason) = crate::generics::unnameable(ty) {
+                if declared {
+                    continue;
+                }
+                return Err(syn::Error::new_spanned(
+                    &field.expr,
+                    format!(
+                        "can't infer the type of `{}` - the type of `{}` has {}, add one like `{}: Type = ..`",
+                        field.ident, param, reason, field.ident
+                    ),
+                ));
+            }
+            field.ty = Some(ty.clone());
+        }
+        Ok(())
+    }
+    /// Make the arguments of a trailing message refer to fields, as it's formatted with only the fields in scope.
+    ///
+    /// Arguments naming a field are left alone, and those naming one of `params`, the function's parameters,
+    /// are added as a field of the same type. Anything else can't be evaluated where the message is formatted.
+    pub fn capture_message_args(&mut self, params: &[(Ident, Type)]) -> syn::Result<()> {
+        let Some(mut message) = self.message.take() else {
+            return Ok(());
+        };
+        for arg in message.iter_mut().skip(1) {
+            let name = expr_ident(arg).cloned();
+            let is_field = |name: &Ident| match &self.fields {
+                MultipleFieldsWithValues::Named(it) => it.fields.iter().any(|it| it.ident == *name),
+                MultipleFieldsWithValues::Unnamed(it) => {
+                    (0..it.fields.len()).any(|index| *name == format!("_{index}"))
+                }
+                MultipleFieldsWithValues::Unit => false,
+            };
+            let param = match &name {
+                Some(name) if is_field(name) => continue,
+                Some(name) => params.iter().find(|(ident, _)| ident == name),
+                None => None,
+            };
+            let Some((param, ty)) = param else {
+                return Err(syn::Error::new_spanned(
+                    arg,
+                    format!(
+                        "message arguments must be fields of `{}` or parameters of the function, as the message is formatted from the fields - add this as a field to use it",
+                        self.ident
+                    ),
+                ));
+            };
+            if let Some(reason) = crate::generics::unnameable(ty) {
+                return Err(syn::Error::new_spanned(
+                    arg,
+                    format!(
+                        "can't capture `{}` in the message - its type has {}, add it as a field with a type",
+                        param, reason
+                    ),
+                ));
+            }
+            let field = match &self.fields {
+                MultipleFieldsWithValues::Unnamed(it) => format_ident!("_{}", it.fields.len()),
+                _ => param.clone(),
+            };
+            self.push_field(param.clone(), ty.clone(), ident_expr(param));
+            *arg = ident_expr(&field);
+        }
+        self.message = Some(message);
+        Ok(())
+    }
+    /// Read untyped fields whose value could also be a type, like `id: u64`, as shorthand for `id: u64 = id`.
+    ///
+    /// This should only be done if the variant's types aren't given elsewhere, or `id: count` would be a value.
+    pub fn use_shorthand(&mut self) {
+        let MultipleFieldsWithValues::Named(fields) = &mut self.fields else {
+            return;
+        };
+        for field in fields.fields.iter_mut() {
+            // `id: id` is a value
+            if field.ty.is_some()
+                || field.colon_token.is_none()
+                || field.expr == ident_expr(&field.ident)
+            {
+                continue;
+            }
+            // `id: count` is a value, unless it names a primitive like `id: u64`, and so is a constant like `id: MAX_ID`
+            if let Some(name) = expr_ident(&field.expr) {
+                let name = name.to_string();
+                if (name.starts_with(|c: char| c.is_lowercase()) && !is_primitive(&name))
+                    || is_constant(&name)
+                {
+                    continue;
+                }
+            }
+            if let Ok(ty) = syn::parse2::<Type>(field.expr.to_token_stream()) {
+                field.ty = Some(ty);
+                field.expr = ident_expr(&field.ident);
+            }
+        }
+    }
+    /// Add a field to the end of this variant, turning a unit variant into a struct variant.
+    pub fn push_field(&mut self, ident: Ident, ty: Type, expr: Expr) {
+        if let MultipleFieldsWithValues::Unit = self.fields {
+            self.fields = MultipleFieldsWithValues::Named(MultipleFieldsWithValueNamed {
+                brace_token: Default::default(),
+                fields: Punctuated::new(),
+            })
+        }
+        match &mut self.fields {
+            MultipleFieldsWithValues::Named(it) => it.fields.push(FieldWithValueNamed {
+                attrs: vec![],
+                ident,
+                colon_token: Some(Default::default()),
+                ty: Some(ty),
+                eq_token: Some(Default::default()),
+                expr,
+            }),
+            MultipleFieldsWithValues::Unnamed(it) => it.fields.push(FieldWithValueUnnamed {
+                attrs: vec![],
+                ty: Some(ty),
+                eq_token: Some(Default::default()),
+                expr,
+            }),
+            MultipleFieldsWithValues::Unit => unreachable!(),
+        }
+    }
     pub fn into_syn_expr_with_prefix(self, mut prefix: Path) -> syn::Expr {
         prefix.segments.push(PathSegment::from(self.ident));
         let path = prefix;
@@ -39,8 +244,16 @@ impl VariantWithValue {
                              ..
                          }| FieldValue {
                             attrs: vec![],
+                            // Use the shorthand `Variant { ident }` where we can
+                            colon_token: match expr {
+                                Expr::Path(ExprPath {
+                                    qself: None,
+                                    ref path,
+                                    ..
+                                }) if path.is_ident(&ident) => None,
+                                _ => Some(colon_token.unwrap_or_default()),
+                            },
                             member: syn::Member::Named(ident),
-                            colon_token: Some(colon_token),
                             expr,
                         },
                     )
@@ -76,8 +289,12 @@ impl VariantWithValue {
 
 impl From<VariantWithValue> for Variant {
     fn from(value: VariantWithValue) -> Self {
+        let mut attrs = value.attrs;
+        if let Some(message) = value.message {
+            attrs.push(parse_quote!(#[display(#message)]))
+        }
         Self {
-            attrs: value.attrs,
+            attrs,
             ident: value.ident,
             fields: value.fields.into(),
             discriminant: value.discriminant,
@@ -104,11 +321,22 @@ impl Parse for VariantWithValue {
         } else {
             None
         };
+        let message = if input.peek(Token![,]) {
+            let _comma: Token![,] = input.parse()?;
+            match input.is_empty() {
+                true => None,
+                false if input.peek(LitStr) => Some(Punctuated::parse_terminated(input)?),
+                false => return Err(input.error("expected a format string")),
+            }
+        } else {
+            None
+        };
         Ok(Self {
             attrs,
             ident,
             fields,
             discriminant,
+            message,
         })
     }
 }
@@ -120,6 +348,16 @@ pub enum MultipleFieldsWithValues {
     Unit,
 }
 
+impl MultipleFieldsWithValues {
+    fn is_typed(&self) -> bool {
+        match self {
+            MultipleFieldsWithValues::Named(it) => it.fields.iter().all(|it| it.ty.is_some()),
+            MultipleFieldsWithValues::Unnamed(it) => it.fields.iter().all(|it| it.ty.is_some()),
+            MultipleFieldsWithValues::Unit => true,
+        }
+    }
+}
+
 impl From<MultipleFieldsWithValues> for Fields {
     fn from(value: MultipleFieldsWithValues) -> Self {
         match value {
@@ -155,13 +393,18 @@ impl Parse for MultipleFieldsWithValueNamed {
     }
 }
 
+/// `ident: Type = expr`, or `ident: expr` if the variant's types are given elsewhere.
+///
+/// `ident: Type` is shorthand for `ident: Type = ident`, like `Foo { ident }` - see [`VariantWithValue::use_shorthand`].
+/// `ident = expr`, or just `ident`, take their type from a parameter of the function - see [`VariantWithValue::infer_types`].
 #[derive(Debug, Clone, PartialEq)]
 pub struct FieldWithValueNamed {
     pub attrs: Vec<Attribute>,
     pub ident: Ident,
-    pub colon_token: Token![:],
-    pub ty: Type,
-    pub eq_token: Token![=],
+    /// `None` if the type should be inferred.
+    pub colon_token: Option<Token![:]>,
+    pub ty: Option<Type>,
+    pub eq_token: Option<Token![=]>,
     pub expr: Expr,
 }
 
@@ -171,8 +414,8 @@ impl From<FieldWithValueNamed> for Field {
             attrs: value.attrs,
             vis: Visibility::Inherited,
             ident: Some(value.ident),
-            colon_token: Some(value.colon_token),
-            ty: value.ty,
+            colon_token: Some(value.colon_token.unwrap_or_default()),
+            ty: value.ty.unwrap_or_else(|| Type::Infer(parse_quote!(_))),
             mutability: syn::FieldMutability::None,
         }
     }
@@ -180,14 +423,125 @@ impl From<FieldWithValueNamed> for Field {
 
 impl Parse for FieldWithValueNamed {
     fn parse(input: ParseStream) -> syn::Result<Self> {
-        Ok(Self {
-            attrs: input.call(Attribute::parse_outer)?,
-            ident: input.parse()?,
-            colon_token: input.parse()?,
-            ty: input.parse()?,
-            eq_token: input.parse()?,
-            expr: input.parse()?,
-        })
+        let attrs = input.call(Attribute::parse_outer)?;
+        let ident: Ident = input.parse()?;
+        if input.peek(Token![:]) {
+            let colon_token = input.parse()?;
+            let (ty, eq_token) = parse_optional_type(input)?;
+            if ty.is_none() && is_only_type(input) {
+                return Ok(Self {
+                    attrs,
+                    colon_token: Some(colon_token),
+                    ty: Some(input.parse()?),
+                    eq_token: None,
+                    expr: ident_expr(&ident),
+                    ident,
+                });
+            }
+            Ok(Self {
+                attrs,
+                ident,
+                colon_token: Some(colon_token),
+                ty,
+                eq_token,
+                expr: input.parse()?,
+            })
+        } else if input.peek(Token![=]) {
+            Ok(Self {
+                attrs,
+                ident,
+                colon_token: None,
+                ty: None,
+                eq_token: Some(input.parse()?),
+                expr: input.parse()?,
+            })
+        } else if input.is_empty() || input.peek(Token![,]) {
+            Ok(Self {
+                attrs,
+                colon_token: None,
+                ty: None,
+                eq_token: None,
+                expr: ident_expr(&ident),
+                ident,
+            })
+        } else {
+            Err(input.error("expected `:`, `=` or `,`"))
+        }
+    }
+}
+
+/// Whether the rest of a field is a `Type` which can't also be read as an expression, like `Vec<u8>`.
+fn is_only_type(input: ParseStream) -> bool {
+    let ends = |fork: ParseStream| fork.is_empty() || fork.peek(Token![,]);
+    let (as_type, as_expr) = (input.fork(), input.fork());
+    as_type.parse::<Type>().is_ok()
+        && ends(&as_type)
+        && !(as_expr.parse::<Expr>().is_ok() && ends(&as_expr))
+}
+
+/// The identifier `expr` consists of, if it's just one.
+fn expr_ident(expr: &Expr) -> Option<&Ident> {
+    match expr {
+        Expr::Path(ExprPath {
+            qself: None, path, ..
+        }) => path.get_ident(),
+        _ => None,
+    }
+}
+
+/// Whether `name` is in screaming snake case, like a constant, rather than a type.
+///
+/// Single letters are left as types, since they're usually generic parameters.
+fn is_constant(name: &str) -> bool {
+    name.len() > 1
+        && name
+            .chars()
+            .all(|c| c.is_uppercase() || c.is_ascii_digit() || c == '_')
+}
+
+/// Whether `name` is a primitive type, rather than a local variable.
+pub fn is_primitive(name: &str) -> bool {
+    matches!(
+        name,
+        "bool"
+            | "char"
+            | "str"
+            | "u8"
+            | "u16"
+            | "u32"
+            | "u64"
+            | "u128"
+            | "usize"
+            | "i8"
+            | "i16"
+            | "i32"
+            | "i64"
+            | "i128"
+            | "isize"
+            | "f32"
+            | "f64"
+    )
+}
+
+fn ident_expr(ident: &Ident) -> Expr {
+    Expr::Path(ExprPath {
+        attrs: vec![],
+        qself: None,
+        path: ident.clone().into(),
+    })
+}
+
+/// Parse a `Type =` if there is one.
+fn parse_optional_type(input: ParseStream) -> syn::Result<(Option<Type>, Option<Token![=]>)> {
+    let fork = input.fork();
+    if fork.parse::<Type>().is_ok()
+        && fork.peek(Token![=])
+        && !fork.peek(Token![==])
+        && !fork.peek(Token![=>])
+    {
+        Ok((Some(input.parse()?), Some(input.parse()?)))
+    } else {
+        Ok((None, None))
     }
 }
 
@@ -216,11 +570,12 @@ impl Parse for MultipleFieldsWithValuesUnnamed {
     }
 }
 
+/// `Type = expr`, or `expr` if the variant's types are given elsewhere.
 #[derive(Debug, Clone, PartialEq)]
 pub struct FieldWithValueUnnamed {
     pub attrs: Vec<Attribute>,
-    pub ty: Type,
-    pub eq_token: Token![=],
+    pub ty: Option<Type>,
+    pub eq_token: Option<Token![=]>,
     pub expr: Expr,
 }
 
@@ -231,7 +586,7 @@ impl From<FieldWithValueUnnamed> for Field {
             vis: Visibility::Inherited,
             ident: None,
             colon_token: None,
-            ty: value.ty,
+            ty: value.ty.unwrap_or_else(|| Type::Infer(parse_quote!(_))),
             mutability: syn::FieldMutability::None,
         }
     }
@@ -239,15 +594,175 @@ impl From<FieldWithValueUnnamed> for Field {
 
 impl Parse for FieldWithValueUnnamed {
     fn parse(input: ParseStream) -> syn::Result<Self> {
+        let attrs = input.call(Attribute::parse_outer)?;
+        let (ty, eq_token) = parse_optional_type(input)?;
         Ok(Self {
-            attrs: input.call(Attribute::parse_outer)?,
-            ty: input.parse()?,
-            eq_token: input.parse()?,
+            attrs,
+            ty,
+            eq_token,
             expr: input.parse()?,
         })
     }
 }
 
+/// `try_err!(Variant(Type), expr)`, which wraps the error returned by `expr` in a single-field variant.
+///
+/// The type may be left out if the variant is declared elsewhere.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TryErr {
+    pub attrs: Vec<Attribute>,
+    pub ident: Ident,
+    pub fields: Option<FieldsUnnamed>,
+    pub comma_token: Token![,],
+    pub expr: Expr,
+    /// Fields which errgo adds after the wrapped error, with their values.
+    pub hidden: Vec<(Type, Expr)>,
+}
+
+impl TryErr {
+    /// The wrapping variant, if its type was given here.
+    pub fn to_syn_variant(&self) -> Option<Variant> {
+        self.fields.as_ref().map(|fields| {
+            let mut fields = fields.clone();
+            fields
+                .unnamed
+                .extend(self.hidden.iter().map(|(ty, _)| Field {
+                    attrs: vec![],
+                    vis: Visibility::Inherited,
+                    ident: None,
+                    colon_token: None,
+                    ty: ty.clone(),
+                    mutability: syn::FieldMutability::None,
+                }));
+            Variant {
+                attrs: self.attrs.clone(),
+                ident: self.ident.clone(),
+                fields: Fields::Unnamed(fields),
+                discriminant: None,
+            }
+        })
+    }
+    pub fn into_syn_expr_with_prefix(self, mut prefix: Path) -> syn::Expr {
+        prefix.segments.push(PathSegment::from(self.ident));
+        let Self { expr, hidden, .. } = self;
+        let values = hidden.iter().map(|(_, value)| value);
+        match hidden.is_empty() {
+            true => parse_quote!(::core::result::Result::map_err(#expr, #prefix)),
+            false => parse_quote! {
+                ::core::result::Result::map_err(#expr, |source| #prefix(source, #(#values),*))
+            },
+        }
+    }
+}
+
+impl Parse for TryErr {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let attrs = input.call(Attribute::parse_outer)?;
+        let ident = input.parse()?;
+        let fields = match input.peek(token::Paren) {
+            true => {
+                let fields: FieldsUnnamed = input.parse()?;
+                if fields.unnamed.len() != 1 {
+                    return Err(syn::Error::new_spanned(
+                        fields,
+                        "expected a single error type, like `try_err!(Io(io::Error), op())`",
+                    ));
+                }
+                Some(fields)
+            }
+            false => None,
+        };
+        let comma_token = input.parse()?;
+        let expr = input.parse()?;
+        let _trailing: Option<Token![,]> = input.parse()?;
+        Ok(Self {
+            attrs,
+            ident,
+            fields,
+            comma_token,
+            expr,
+            hidden: Vec::new(),
+        })
+    }
+}
+
+/// `check!(expr, Variant)`, which fails with a variant carrying the name of the call and `errno` if `expr` is negative.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Check {
+    pub expr: Expr,
+    pub comma_token: Token![,],
+    pub attrs: Vec<Attribute>,
+    pub ident: Ident,
+}
+
+impl Check {
+    /// The name of the function or method called by `expr`, like `libc::open`.
+    fn call(&self) -> String {
+        let call = match &self.expr {
+            Expr::Call(it) => it.func.to_token_stream(),
+            Expr::MethodCall(it) => it.method.to_token_stream(),
+            other => other.to_token_stream(),
+        };
+        call.to_string().replace(' ', "")
+    }
+    /// The variant to return, like `Open { call: &'static str = "libc::open", errno: i32 = __errgo_errno }`.
+    pub fn variant(&self) -> VariantWithValue {
+        let Self { attrs, ident, .. } = self;
+        let call = LitStr::new(&self.call(), self.ident.span());
+        parse_quote! {
+            #(#attrs)*
+            #ident {
+                call: &'static str = #call,
+                errno: i32 = __errgo_errno
+            }
+        }
+    }
+    /// Evaluate `expr`, failing with `construction` (of [`Self::variant`]) if it's negative.
+    ///
+    /// `wrappers` are the variants the function returns its `Result` in, which `?` can't return through.
+    pub fn into_syn_expr(self, construction: Expr, wrappers: &[Path]) -> Expr {
+        let expr = self.expr;
+        let fail: Expr = match wrappers.is_empty() {
+            true => parse_quote!(::core::result::Result::Err::<(), _>(#construction)?),
+            false => {
+                let mut returned: Expr = parse_quote! {
+                    ::core::result::Result::Err(::core::convert::From::from(#construction))
+                };
+                for wrapper in wrappers.iter().rev() {
+                    returned = parse_quote!(#wrapper(#returned))
+                }
+                parse_quote!(return #returned)
+            }
+        };
+        parse_quote!({
+            let __errgo_ret = #expr;
+            if __errgo_ret < 0 {
+                let __errgo_errno = ::std::io::Error::last_os_error()
+                    .raw_os_error()
+                    .unwrap_or_default();
+                #fail;
+            }
+            __errgo_ret
+        })
+    }
+}
+
+impl Parse for Check {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let expr = input.parse()?;
+        let comma_token = input.parse()?;
+        let attrs = input.call(Attribute::parse_outer)?;
+        let ident = input.parse()?;
+        let _trailing: Option<Token![,]> = input.parse()?;
+        Ok(Self {
+            expr,
+            comma_token,
+            attrs,
+            ident,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::fmt;
@@ -313,6 +828,7 @@ mod tests {
                 ident: ident("Foo"),
                 fields: MultipleFieldsWithValues::Unit,
                 discriminant: None,
+                message: None,
             },
         )
     }
@@ -326,6 +842,7 @@ mod tests {
                 ident: ident("Foo"),
                 fields: MultipleFieldsWithValues::Unit,
                 discriminant: Some((Default::default(), lit_int("1"))),
+                message: None,
             },
         )
     }
@@ -342,13 +859,14 @@ mod tests {
                     fields: Punctuated::from_iter([FieldWithValueNamed {
                         attrs: vec![],
                         ident: ident("bar"),
-                        colon_token: Default::default(),
-                        ty: type_path(["usize"]),
-                        eq_token: Default::default(),
+                        colon_token: Some(Default::default()),
+                        ty: Some(type_path(["usize"])),
+                        eq_token: Some(Default::default()),
                         expr: lit_int("1"),
                     }]),
                 }),
                 discriminant: None,
+                message: None,
             },
         );
     }
@@ -364,16 +882,382 @@ mod tests {
                     paren_token: Default::default(),
                     fields: Punctuated::from_iter([FieldWithValueUnnamed {
                         attrs: vec![],
-                        ty: type_path(["usize"]),
-                        eq_token: Default::default(),
+                        ty: Some(type_path(["usize"])),
+                        eq_token: Some(Default::default()),
                         expr: lit_int("1"),
                     }]),
                 }),
                 discriminant: None,
+                message: None,
             },
         )
     }
 
+    #[test]
+    fn parse_message() {
+        test_parse(
+            quote!(Foo, "need {} foos", n),
+            VariantWithValue {
+                attrs: vec![],
+                ident: ident("Foo"),
+                fields: MultipleFieldsWithValues::Unit,
+                discriminant: None,
+                message: Some(syn::parse_quote!("need {} foos", n)),
+            },
+        );
+        assert_eq!(
+            syn::parse2::<Variant>(quote! {
+                #[display("need {} foos", n)]
+                Foo(usize)
+            })
+            .unwrap(),
+            syn::parse2::<VariantWithValue>(quote!(Foo(usize = 1), "need {} foos", n))
+                .unwrap()
+                .into_syn_variant()
+        );
+        assert!(syn::parse2::<VariantWithValue>(quote!(Foo, n)).is_err());
+    }
+
+    #[test]
+    fn parse_untyped_variants() {
+        for (tokens, expected) in [
+            (quote!(Foo { bar: baz }), quote!(Foo { bar: baz })),
+            (quote!(Foo { bar: a == b }), quote!(Foo { bar: a == b })),
+            (quote!(Foo(baz, 1)), quote!(Foo(baz, 1))),
+        ] {
+            let variant = syn::parse2::<VariantWithValue>(tokens).unwrap();
+            assert_eq!(
+                syn::parse2::<Expr>(expected).unwrap(),
+                variant.clone().into_syn_expr_with_prefix(Path {
+                    leading_colon: None,
+                    segments: Punctuated::new()
+                })
+            );
+            assert!(!variant.is_typed());
+        }
+    }
+
+    #[test]
+    fn fill_types() {
+        for (tokens, declaration, expected) in [
+            (
+                quote!(Foo { b: 1, a: 'a' }),
+                quote!(Foo { a: char, b: usize }),
+                quote!(Foo { b: usize, a: char }),
+            ),
+            (
+                quote!(Foo(1, char = 'a')),
+                quote!(Foo(usize, u8)),
+                quote!(Foo(usize, char)),
+            ),
+        ] {
+            let mut variant = syn::parse2::<VariantWithValue>(tokens).unwrap();
+            variant
+                .fill_types(&syn::parse2(declaration).unwrap())
+                .unwrap();
+            assert_eq!(
+                syn::parse2::<Variant>(expected).unwrap(),
+                variant.into_syn_variant()
+            );
+        }
+        for (tokens, declaration) in [
+            (quote!(Foo { c: 1 }), quote!(Foo { a: char })),
+            (quote!(Foo(1, 2)), quote!(Foo(usize))),
+            (quote!(Foo(1)), quote!(Foo { a: usize })),
+        ] {
+            let mut variant = syn::parse2::<VariantWithValue>(tokens).unwrap();
+            assert!(variant
+                .fill_types(&syn::parse2(declaration).unwrap())
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn infer_types() {
+        let params = [
+            (ident("got"), type_path(["usize"])),
+            (ident("name"), syn::parse2(quote!(&str)).unwrap()),
+        ];
+        for (tokens, expected_construction, expected_definition) in [
+            (
+                quote!(Foo { got }),
+                quote!(Foo { got }),
+                quote!(Foo { got: usize }),
+            ),
+            (
+                quote!(Foo { count = got, b: u8 = 1 }),
+                quote!(Foo { count: got, b: 1 }),
+                quote!(Foo {
+                    count: usize,
+                    b: u8
+                }),
+            ),
+        ] {
+            let mut variant = syn::parse2::<VariantWithValue>(tokens).unwrap();
+            assert!(!variant.is_typed());
+            variant.infer_types(&params, false).unwrap();
+            assert!(variant.is_typed());
+            assert_eq!(
+                syn::parse2::<Expr>(expected_construction).unwrap(),
+                variant.clone().into_syn_expr_with_prefix(Path {
+                    leading_colon: None,
+                    segments: Punctuated::new()
+                })
+            );
+            assert_eq!(
+                syn::parse2::<Variant>(expected_definition).unwrap(),
+                variant.into_syn_variant()
+            );
+        }
+        for tokens in [
+            quote!(Foo { missing }),
+            quote!(Foo { count = got + 1 }),
+            quote!(Foo { name }),
+        ] {
+            let mut variant = syn::parse2::<VariantWithValue>(tokens).unwrap();
+            assert!(variant.infer_types(&params, false).is_err());
+        }
+        // As are other values, if the variant is declared elsewhere
+        for tokens in [quote!(Foo { missing }), quote!(Foo { count = got + 1 })] {
+            let mut variant = syn::parse2::<VariantWithValue>(tokens).unwrap();
+            variant.infer_types(&params, true).unwrap();
+            assert!(!variant.is_typed());
+        }
+        // `ident: expr` is left for `fill_types`
+        let mut variant = syn::parse2::<VariantWithValue>(quote!(Foo { count: got })).unwrap();
+        variant.infer_types(&params, false).unwrap();
+        assert!(!variant.is_typed());
+        assert!(syn::parse2::<VariantWithValue>(quote!(Foo { got 1 })).is_err());
+    }
+
+    #[test]
+    fn capture_message_args() {
+        let params = [(ident("n"), type_path(["usize"]))];
+        for (tokens, expected_construction, expected_definition) in [
+            (
+                quote!(Foo, "{} {}", n, n),
+                quote!(Foo { n }),
+                quote!(
+                    #[display("{} {}", n, n)]
+                    Foo { n: usize }
+                ),
+            ),
+            (
+                quote!(Foo(u8 = 1), "{} {}", _0, n),
+                quote!(Foo(1, n)),
+                quote!(
+                    #[display("{} {}", _0, _1)]
+                    Foo(u8, usize)
+                ),
+            ),
+        ] {
+            let mut variant = syn::parse2::<VariantWithValue>(tokens).unwrap();
+            variant.capture_message_args(&params).unwrap();
+            assert_eq!(
+                syn::parse2::<Expr>(expected_construction).unwrap(),
+                variant.clone().into_syn_expr_with_prefix(Path {
+                    leading_colon: None,
+                    segments: Punctuated::new()
+                })
+            );
+            assert_eq!(
+                syn::parse2::<Variant>(expected_definition).unwrap(),
+                variant.into_syn_variant()
+            );
+        }
+        for tokens in [quote!(Foo, "{}", m), quote!(Foo(u8 = 1), "{}", n + 1)] {
+            let mut variant = syn::parse2::<VariantWithValue>(tokens).unwrap();
+            assert!(variant.capture_message_args(&params).is_err());
+        }
+    }
+
+    #[test]
+    fn shorthand() {
+        for (tokens, expected_definition) in [
+            (
+                quote!(Conflict { id: u64, name: Vec<u8> }),
+                quote!(Conflict { id: u64, name: Vec<u8> }),
+            ),
+            (
+                quote!(Conflict { id: u64, name: &'a str, count: usize = 1 }),
+                quote!(Conflict { id: u64, name: &'a str, count: usize }),
+            ),
+            (
+                quote!(Conflict {
+                    id: crate::Id,
+                    pair: (u8, u8)
+                }),
+                quote!(Conflict {
+                    id: crate::Id,
+                    pair: (u8, u8)
+                }),
+            ),
+        ] {
+            let mut variant = syn::parse2::<VariantWithValue>(tokens).unwrap();
+            variant.use_shorthand();
+            assert!(variant.is_typed());
+            let construction = variant.clone().into_syn_expr_with_prefix(Path {
+                leading_colon: None,
+                segments: Punctuated::new(),
+            });
+            let Expr::Struct(construction) = construction else {
+                panic!("expected a struct expression")
+            };
+            assert!(construction.fields.iter().all(|it| {
+                it.member == syn::Member::Named(ident("count")) || it.colon_token.is_none()
+            }));
+            assert_eq!(
+                syn::parse2::<Variant>(expected_definition).unwrap(),
+                variant.into_syn_variant()
+            );
+        }
+        // Types which are also expressions wait to see if the variant is declared elsewhere
+        let mut variant =
+            syn::parse2::<VariantWithValue>(quote!(Conflict { id: next_id, name: Vec<u8> }))
+                .unwrap();
+        assert!(!variant.is_typed());
+        variant
+            .fill_types(&syn::parse2(quote!(Conflict { id: u64, name: Vec<u8> })).unwrap())
+            .unwrap();
+        assert_eq!(
+            syn::parse2::<Expr>(quote!(Conflict { id: next_id, name })).unwrap(),
+            variant.into_syn_expr_with_prefix(Path {
+                leading_colon: None,
+                segments: Punctuated::new()
+            })
+        );
+        for tokens in [
+            quote!(Foo { bar: a == b }),
+            quote!(Foo { bar: bar }),
+            quote!(Foo { n: count }),
+            quote!(Overflow { code: MAX_CODE }),
+        ] {
+            let mut variant = syn::parse2::<VariantWithValue>(tokens).unwrap();
+            variant.use_shorthand();
+            assert!(!variant.is_typed());
+        }
+        // Though a single letter is a generic parameter
+        let mut variant = syn::parse2::<VariantWithValue>(quote!(Foo { t: T })).unwrap();
+        variant.use_shorthand();
+        assert!(variant.is_typed());
+    }
+
+    #[test]
+    fn push_fields() {
+        for (variant, expected) in [
+            (quote!(Foo), quote!(Foo { __x: X })),
+            (quote!(Foo(u8 = 1)), quote!(Foo(u8, X))),
+            (quote!(Foo { a: u8 = 1 }), quote!(Foo { a: u8, __x: X })),
+        ] {
+            let mut variant = syn::parse2::<VariantWithValue>(variant).unwrap();
+            variant.push_field(ident("__x"), type_path(["X"]), parse_quote!(X(1)));
+            assert_eq!(
+                syn::parse2::<Variant>(expected).unwrap(),
+                variant.into_syn_variant()
+            );
+        }
+    }
+
+    #[test]
+    fn parse_try_err() {
+        let try_err = syn::parse2::<TryErr>(quote!(
+            #[error("io")]
+            Io(io::Error),
+            op(),
+        ))
+        .unwrap();
+        assert_eq!(
+            Some(
+                syn::parse2::<Variant>(quote!(
+                    #[error("io")]
+                    Io(io::Error)
+                ))
+                .unwrap()
+            ),
+            try_err.to_syn_variant()
+        );
+        assert_eq!(
+            syn::parse2::<Expr>(quote!(::core::result::Result::map_err(op(), FooError::Io)))
+                .unwrap(),
+            try_err.into_syn_expr_with_prefix(path(["FooError"]))
+        );
+
+        let try_err = syn::parse2::<TryErr>(quote!(Io, op())).unwrap();
+        assert_eq!(None, try_err.to_syn_variant());
+
+        for tokens in [
+            quote!(Io(io::Error, u8), op()),
+            quote!(Io()),
+            quote!(Io { e: io::Error }, op()),
+        ] {
+            assert!(syn::parse2::<TryErr>(tokens).is_err());
+        }
+    }
+
+    #[test]
+    fn check() {
+        let check = syn::parse2::<Check>(quote!(
+            libc::open(path, 0),
+            #[error("open")]
+            Open
+        ))
+        .unwrap();
+        assert_eq!(
+            syn::parse2::<Variant>(quote!(
+                #[error("open")]
+                Open {
+                    call: &'static str,
+                    errno: i32
+                }
+            ))
+            .unwrap(),
+            check.variant().into_syn_variant()
+        );
+        let construction = quote!(FooError::Open {
+            call: "libc::open",
+            errno: __errgo_errno
+        });
+        assert_eq!(
+            syn::parse2::<Expr>(quote!({
+                let __errgo_ret = libc::open(path, 0);
+                if __errgo_ret < 0 {
+                    let __errgo_errno = ::std::io::Error::last_os_error()
+                        .raw_os_error()
+                        .unwrap_or_default();
+                    ::core::result::Result::Err::<(), _>(#construction)?;
+                }
+                __errgo_ret
+            }))
+            .unwrap(),
+            check
+                .clone()
+                .into_syn_expr(syn::parse2(construction.clone()).unwrap(), &[])
+        );
+        // `?` can't return through an `Option`
+        assert_eq!(
+            syn::parse2::<Expr>(quote!({
+                let __errgo_ret = libc::open(path, 0);
+                if __errgo_ret < 0 {
+                    let __errgo_errno = ::std::io::Error::last_os_error()
+                        .raw_os_error()
+                        .unwrap_or_default();
+                    return ::core::option::Option::Some(::core::result::Result::Err(
+                        ::core::convert::From::from(#construction)
+                    ));
+                }
+                __errgo_ret
+            }))
+            .unwrap(),
+            check.into_syn_expr(
+                syn::parse2(construction).unwrap(),
+                &[parse_quote!(::core::option::Option::Some)]
+            )
+        );
+
+        let check = syn::parse2::<Check>(quote!(file.close(), Close)).unwrap();
+        assert_eq!("close", check.call());
+    }
+
     #[test]
     fn use_unit_variant() {
         test_use(quote!(Foo), quote!(Foo), quote!(Foo))
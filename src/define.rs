@@ -0,0 +1,66 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{punctuated::Punctuated, Ident, Path, Token};
+
+/// The name of the macro which defines the error called `name`, for [`collect`] to invoke.
+fn macro_name(name: &Ident) -> Ident {
+    format_ident!("__errgo_define_{}", name)
+}
+
+/// The path to the error called `name` once it's defined in `module`.
+pub fn qualify(module: &Path, name: &Ident) -> Path {
+    let mut path = module.clone();
+    path.segments.push(name.clone().into());
+    path
+}
+
+/// Wrap the `definition` of the error called `name` in a macro, to be invoked by `collect!` in the `define_in` module.
+pub fn wrap(name: &Ident, definition: TokenStream) -> TokenStream {
+    let macro_name = macro_name(name);
+    quote! {
+        #[doc(hidden)]
+        macro_rules! #macro_name {
+            () => {
+                #definition
+            };
+        }
+        #[doc(hidden)]
+        #[allow(unused_imports)]
+        pub(crate) use #macro_name;
+    }
+}
+
+/// Define each of the `errors`, named by the module of the function which declares them, like `crate::api::FooError`.
+pub fn collect(errors: Punctuated<Path, Token![,]>) -> TokenStream {
+    let invocations = errors.into_iter().map(|mut path| {
+        if let Some(last) = path.segments.last_mut() {
+            last.ident = macro_name(&last.ident)
+        }
+        quote!(#path!();)
+    });
+    quote!(#(#invocations)*)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ident, path};
+    use pretty_assertions::assert_eq;
+    use syn::parse_quote;
+
+    #[test]
+    fn collect_errors() {
+        let expected = quote! {
+            super::__errgo_define_FooError!();
+            crate::api::__errgo_define_BarError!();
+        };
+        assert_eq!(
+            expected.to_string(),
+            collect(parse_quote!(super::FooError, crate::api::BarError)).to_string()
+        );
+        assert_eq!(
+            path(["crate", "errors", "FooError"]),
+            qualify(&path(["crate", "errors"]), &ident("FooError"))
+        );
+    }
+}
@@ -0,0 +1,443 @@
+use std::collections::BTreeSet;
+
+use proc_macro2::TokenStream;
+use proc_macro_error::emit_error;
+use quote::{format_ident, quote};
+use syn::{
+    parse::{Parse, ParseStream},
+    parse_quote,
+    punctuated::Punctuated,
+    Attribute, Expr, Generics, Ident, LitStr, Token, Type, TypePath, Variant,
+};
+
+use crate::{
+    config::Config,
+    hidden::{is_hidden, Hidden},
+    structs::Layout,
+};
+
+/// The arguments to a `#[display("format string", args..)]` attribute.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    pub format: LitStr,
+    pub args: Punctuated<Expr, Token![,]>,
+}
+
+impl Parse for Message {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let format = input.parse()?;
+        let args = match input.is_empty() {
+            true => Punctuated::new(),
+            false => {
+                let _comma: Token![,] = input.parse()?;
+                Punctuated::parse_terminated(input)?
+            }
+        };
+        Ok(Self { format, args })
+    }
+}
+
+impl Message {
+    /// A `write!` of this message to `__formatter`, with the variant's fields in scope.
+    fn write(&self) -> TokenStream {
+        let Self { format, args } = self;
+        match args.is_empty() {
+            true => {
+                let (rewritten, positions) = rewrite_positional(&format.value());
+                let format = LitStr::new(&rewritten, format.span());
+                // Passed by name rather than captured, as the format string may be from another hygiene context
+                let bindings = positions.iter().map(|index| format_ident!("_{}", index));
+                quote!(::core::write!(__formatter, #format #(, #bindings = #bindings)*))
+            }
+            false => quote!(::core::write!(__formatter, #format, #args)),
+        }
+    }
+}
+
+/// Rewrite positional arguments like `{0}` to refer to the bindings of a tuple variant's fields, like `{_0}`,
+/// returning the positions which were used.
+fn rewrite_positional(format: &str) -> (String, BTreeSet<usize>) {
+    let mut positions = BTreeSet::new();
+    let mut rewritten = String::with_capacity(format.len());
+    let mut chars = format.chars().peekable();
+    while let Some(c) = chars.next() {
+        rewritten.push(c);
+        match c {
+            '{' | '}' if chars.peek() == Some(&c) => rewritten.push(chars.next().unwrap()),
+            '{' => {
+                let mut digits = String::new();
+                while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+                    digits.push(digit)
+                }
+                if !digits.is_empty() && matches!(chars.peek(), Some('}' | ':')) {
+                    rewritten.push('_');
+                    positions.extend(digits.parse::<usize>());
+                }
+                rewritten.push_str(&digits)
+            }
+            _ => {}
+        }
+    }
+    (rewritten, positions)
+}
+
+pub fn is_display_attr(attr: &Attribute) -> bool {
+    attr.path().is_ident("display")
+}
+
+/// Whether `ty` is known to implement `Display`, which reads better than `Debug` -
+/// a primitive, `str` or `String`, or a reference to one.
+fn displays(ty: &Type) -> bool {
+    match ty {
+        Type::Reference(it) => displays(&it.elem),
+        Type::Paren(it) => displays(&it.elem),
+        Type::Group(it) => displays(&it.elem),
+        Type::Path(TypePath { qself: None, path }) => path.segments.last().is_some_and(|it| {
+            it.arguments.is_empty()
+                && (it.ident == "String" || crate::data::is_primitive(&it.ident.to_string()))
+        }),
+        _ => false,
+    }
+}
+
+/// Give each variant without a message one built from its name and fields, like
+/// `#[error("not enough buckets (got: {got}, required: {required})")]`.
+///
+/// Fields are formatted with `Display` if they're known to implement it, else `Debug`.
+/// The message is added as a `#[display(..)]` attribute if `display` is set, or `#[error(..)]` for thiserror.
+/// Any `hidden` fields are left out.
+pub fn add_defaults(variants: &mut [Variant], hidden: &[Hidden], display: bool) {
+    for variant in variants {
+        let has_message = variant
+            .attrs
+            .iter()
+            .any(|attr| is_display_attr(attr) || attr.path().is_ident("error"));
+        if has_message {
+            continue;
+        }
+        let mut format = crate::case::lower_words(&variant.ident);
+        let fields = variant
+            .fields
+            .iter()
+            .enumerate()
+            .filter(|(_, field)| !is_hidden(field, hidden))
+            .map(|(index, field)| {
+                let spec = match displays(&field.ty) {
+                    true => "",
+                    false => ":?",
+                };
+                match &field.ident {
+                    Some(ident) => format!("{ident}: {{{ident}{spec}}}"),
+                    None => format!("{{{index}{spec}}}"),
+                }
+            })
+            .collect::<Vec<_>>();
+        if !fields.is_empty() {
+            format.push_str(&format!(" ({})", fields.join(", ")))
+        }
+        let format = LitStr::new(&format, variant.ident.span());
+        variant.attrs.push(match display {
+            true => parse_quote!(#[display(#format)]),
+            false => parse_quote!(#[error(#format)]),
+        })
+    }
+}
+
+/// Implement `Display` and `Error` for the generated enum, taking the `#[display(..)]` attributes from each variant.
+///
+/// The variants are reached through `layout`, and `Error::source` returns each variant's [source field](crate::source::find), if it has one.
+pub fn implement(
+    name: &Ident,
+    generics: &Generics,
+    variants: &mut [Variant],
+    config: &Config,
+    layout: Layout<'_>,
+) -> TokenStream {
+    let source_arms = variants
+        .iter_mut()
+        .filter_map(|variant| {
+            let index = crate::source::find(&variant.fields, config.source_by_type)?;
+            for field in variant.fields.iter_mut() {
+                field.attrs.retain(|it| {
+                    !crate::source::is_source_attr(it) && !crate::source::is_from_attr(it)
+                })
+            }
+            let binding = match &variant.fields.iter().nth(index)?.ident {
+                Some(ident) => ident.clone(),
+                None => format_ident!("_{}", index),
+            };
+            let pattern = crate::pattern(variant, layout);
+            Some(quote! {
+                #pattern => ::core::option::Option::Some(#binding as &(dyn ::std::error::Error + 'static))
+            })
+        })
+        .collect::<Vec<_>>();
+    let source = match source_arms.is_empty() {
+        true => quote!(),
+        false => quote! {
+            fn source(&self) -> ::core::option::Option<&(dyn ::std::error::Error + 'static)> {
+                #[allow(unused_variables)]
+                match self {
+                    #(#source_arms,)*
+                    #[allow(unreachable_patterns)]
+                    _ => ::core::option::Option::None,
+                }
+            }
+        },
+    };
+
+    let arms = variants
+        .iter_mut()
+        .map(|variant| {
+            let message = variant
+                .attrs
+                .iter()
+                .position(is_display_attr)
+                .map(|position| variant.attrs.remove(position).parse_args::<Message>());
+            let pattern = crate::pattern(variant, layout);
+            let body = match message {
+                Some(Ok(message)) => message.write(),
+                Some(Err(e)) => {
+                    emit_error!(e.span(), "{}", e);
+                    quote!(::core::result::Result::Ok(()))
+                }
+                None => {
+                    emit_error!(
+                        variant.ident,
+                        "`{}` has no message - add one like `err!({}, \"...\")` or `#[display(\"...\")]`",
+                        variant.ident,
+                        variant.ident
+                    );
+                    quote!(::core::result::Result::Ok(()))
+                }
+            };
+            quote!(#pattern => #body)
+        })
+        .collect::<Vec<_>>();
+    let body = match arms.is_empty() {
+        true => quote!(match *self {}),
+        false => quote! {
+            #[allow(unused_variables)]
+            match self {
+                #(#arms,)*
+            }
+        },
+    };
+
+    let provide = match config.provide {
+        true => crate::hidden::provide(),
+        false => quote!(),
+    };
+
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let mut error_generics = generics.clone();
+    error_generics
+        .make_where_clause()
+        .predicates
+        .push(parse_quote!(Self: ::core::fmt::Debug));
+    let error_where_clause = &error_generics.where_clause;
+
+    quote! {
+        impl #impl_generics ::core::fmt::Display for #name #ty_generics #where_clause {
+            fn fmt(&self, __formatter: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                #body
+            }
+        }
+
+        impl #impl_generics ::std::error::Error for #name #ty_generics #error_where_clause {
+            #source
+            #provide
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn rewrite() {
+        for (format, expected, positions) in [
+            ("{0} and {1:?}", "{_0} and {_1:?}", vec![0, 1]),
+            ("{{0}} and {}", "{{0}} and {}", vec![]),
+            ("{named} {0x}", "{named} {0x}", vec![]),
+            ("{10:>width$}}} {10}", "{_10:>width$}}} {_10}", vec![10]),
+        ] {
+            let (rewritten, used) = rewrite_positional(format);
+            assert_eq!(expected, rewritten);
+            assert_eq!(positions, used.into_iter().collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn implement_display() {
+        let mut variants = [
+            parse_quote!(
+                #[display("unit")]
+                Unit
+            ),
+            parse_quote!(
+                #[display("{0}")]
+                #[allow(unused)]
+                Tuple(usize)
+            ),
+            parse_quote!(
+                #[display("{} and {b}", a)]
+                Named { a: usize, b: usize }
+            ),
+        ];
+        let implemented = implement(
+            &crate::ident("FooError"),
+            &Generics::default(),
+            &mut variants,
+            &Config::default(),
+            Layout::Inline,
+        );
+        let expected = quote! {
+            impl ::core::fmt::Display for FooError {
+                fn fmt(&self, __formatter: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                    #[allow(unused_variables)]
+                    match self {
+                        Self::Unit => ::core::write!(__formatter, "unit"),
+                        Self::Tuple(_0) => ::core::write!(__formatter, "{_0}", _0 = _0),
+                        Self::Named { a, b } => ::core::write!(__formatter, "{} and {b}", a),
+                    }
+                }
+            }
+
+            impl ::std::error::Error for FooError where Self: ::core::fmt::Debug {}
+        };
+        assert_eq!(expected.to_string(), implemented.to_string());
+        let expected: [Variant; 3] = [
+            parse_quote!(Unit),
+            parse_quote!(
+                #[allow(unused)]
+                Tuple(usize)
+            ),
+            parse_quote!(Named { a: usize, b: usize }),
+        ];
+        assert_eq!(expected, variants);
+    }
+
+    #[test]
+    fn implement_source() {
+        let mut variants = [
+            parse_quote!(
+                #[display("io")]
+                Io(
+                    u8,
+                    #[source]
+                    io::Error
+                )
+            ),
+            parse_quote!(
+                #[display("parse")]
+                Parse { e: ParseIntError }
+            ),
+            parse_quote!(
+                #[display("unit")]
+                Unit
+            ),
+        ];
+        let implemented = implement(
+            &crate::ident("FooError"),
+            &Generics::default(),
+            &mut variants,
+            &Config {
+                source_by_type: true,
+                ..Default::default()
+            },
+            Layout::Wrapped(&crate::ident("foo_error")),
+        );
+        let expected = quote! {
+            impl ::core::fmt::Display for FooError {
+                fn fmt(&self, __formatter: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                    #[allow(unused_variables)]
+                    match self {
+                        Self::Io(foo_error::Io(_0, _1)) => ::core::write!(__formatter, "io"),
+                        Self::Parse(foo_error::Parse { e }) => ::core::write!(__formatter, "parse"),
+                        Self::Unit(foo_error::Unit) => ::core::write!(__formatter, "unit"),
+                    }
+                }
+            }
+
+            impl ::std::error::Error for FooError where Self: ::core::fmt::Debug {
+                fn source(&self) -> ::core::option::Option<&(dyn ::std::error::Error + 'static)> {
+                    #[allow(unused_variables)]
+                    match self {
+                        Self::Io(foo_error::Io(_0, _1)) => ::core::option::Option::Some(_1 as &(dyn ::std::error::Error + 'static)),
+                        Self::Parse(foo_error::Parse { e }) => ::core::option::Option::Some(e as &(dyn ::std::error::Error + 'static)),
+                        #[allow(unreachable_patterns)]
+                        _ => ::core::option::Option::None,
+                    }
+                }
+            }
+        };
+        assert_eq!(expected.to_string(), implemented.to_string());
+        let expected: [Variant; 3] = [
+            parse_quote!(Io(u8, io::Error)),
+            parse_quote!(Parse { e: ParseIntError }),
+            parse_quote!(Unit),
+        ];
+        assert_eq!(expected, variants);
+    }
+
+    #[test]
+    fn default_messages() {
+        let mut variants: [Variant; 5] = [
+            parse_quote!(NotEnoughBuckets {
+                got: usize,
+                required: usize
+            }),
+            parse_quote!(HTTPFailed(u16, String)),
+            parse_quote!(Missing {
+                name: &'a str,
+                path: PathBuf
+            }),
+            parse_quote!(Timeout),
+            parse_quote!(
+                #[error("given")]
+                Given
+            ),
+        ];
+        add_defaults(&mut variants, &[], false);
+        let expected: [Variant; 5] = [
+            parse_quote!(
+                #[error("not enough buckets (got: {got}, required: {required})")]
+                NotEnoughBuckets {
+                    got: usize,
+                    required: usize
+                }
+            ),
+            parse_quote!(
+                #[error("http failed ({0}, {1})")]
+                HTTPFailed(u16, String)
+            ),
+            parse_quote!(
+                #[error("missing (name: {name}, path: {path:?})")]
+                Missing {
+                    name: &'a str,
+                    path: PathBuf
+                }
+            ),
+            parse_quote!(
+                #[error("timeout")]
+                Timeout
+            ),
+            parse_quote!(
+                #[error("given")]
+                Given
+            ),
+        ];
+        assert_eq!(expected, variants);
+
+        let mut variants: [Variant; 1] = [parse_quote!(Timeout)];
+        add_defaults(&mut variants, &[], true);
+        let expected: [Variant; 1] = [parse_quote!(
+            #[display("timeout")]
+            Timeout
+        )];
+        assert_eq!(expected, variants);
+    }
+}
@@ -0,0 +1,265 @@
+use std::sync::{Mutex, PoisonError};
+
+use proc_macro2::TokenStream;
+use proc_macro_error::{abort, emit_error};
+use quote::{format_ident, quote};
+use syn::{
+    braced, parenthesized,
+    parse::{Parse, ParseStream},
+    punctuated::Punctuated,
+    Fields, Ident, ItemEnum, Token, Type, Variant,
+};
+
+use crate::{code::is_code_attr, config::Config, display::is_display_attr};
+
+/// The name of the macro which brings the variants of a function into the `#[errgo::extendable]` enum called `name`.
+fn bridge_name(name: &Ident) -> Ident {
+    format_ident!("__errgo_extend_{}", name)
+}
+
+/// The enums extended so far while compiling this crate, and the function or module extending each.
+///
+/// Macros don't see each other's output, so this is how a second extension is caught before it defines the enum again.
+static EXTENDED: Mutex<Vec<(String, String)>> = Mutex::new(Vec::new());
+
+/// Record that `extender` extends the enum called `name`, returning what already does if that's something else.
+fn claim(name: &Ident, extender: &Ident) -> Option<String> {
+    let (name, extender) = (name.to_string(), extender.to_string());
+    let mut extended = EXTENDED.lock().unwrap_or_else(PoisonError::into_inner);
+    match extended.iter().find(|(it, _)| *it == name) {
+        Some((_, first)) if *first != extender => Some(first.clone()),
+        Some(_) => None,
+        None => {
+            extended.push((name, extender));
+            None
+        }
+    }
+}
+
+/// Replace a hand-written enum with a macro, which defines it along with the variants it's given.
+///
+/// The enum is also defined as written in a hidden module, and glob imported from there.
+/// That's used if nothing extends it, and is otherwise shadowed by the extended enum.
+pub fn extendable(item: &ItemEnum) -> TokenStream {
+    let bridge = bridge_name(&item.ident);
+    let module = format_ident!("__errgo_{}", item.ident);
+    let mut fallback = item.clone();
+    fallback.vis = crate::inner_visibility(&item.vis);
+    for variant in fallback.variants.iter_mut() {
+        variant
+            .attrs
+            .retain(|it| !is_display_attr(it) && !is_code_attr(it))
+    }
+    let vis = &item.vis;
+    quote! {
+        #[doc(hidden)]
+        macro_rules! #bridge {
+            ($($extension:tt)*) => {
+                ::errgo::__extend! { #item $($extension)* }
+            };
+        }
+        #[doc(hidden)]
+        #[allow(unused_imports)]
+        pub(crate) use #bridge;
+
+        #[doc(hidden)]
+        #[allow(dead_code, non_snake_case, unused_imports)]
+        mod #module {
+            use super::*;
+            #fallback
+        }
+        #[allow(unused_imports)]
+        #vis use #module::*;
+    }
+}
+
+/// Pass the `variants` collected by `extender` for the error called `name` to its enum, through the macro from [`extendable`].
+pub fn bridge(
+    config: &Config,
+    name: &Ident,
+    extender: &Ident,
+    variants: &[Variant],
+    conversions: &[(Ident, Type)],
+) -> TokenStream {
+    if config.location || config.backtrace {
+        emit_error!(
+            name,
+            "`extend` can't be used with `location` or `backtrace` - the hand-written variants of `{}` can't have their fields",
+            name
+        )
+    }
+    if config.variant_structs {
+        emit_error!(
+            name,
+            "`extend` can't be used with `variant_structs` - the hand-written variants of `{}` don't wrap structs",
+            name
+        )
+    }
+    if let Some(visibility) = &config.visibility {
+        emit_error!(
+            visibility,
+            "`extend` can't be used with `visibility` - the visibility of `{}` is written on the enum",
+            name
+        )
+    }
+    // Anything else would be reported as defining the enum twice, so leave nothing else to report
+    if let Some(first) = claim(name, extender) {
+        abort!(
+            extender,
+            "`{}` is already extended by `{}` - only one function may extend it, use `#[errgo(error = {}, extend)]` on a module to extend it from several",
+            name,
+            first,
+            name
+        )
+    }
+    let bridge = bridge_name(name);
+    let conversions = conversions.iter().map(|(ident, ty)| quote!(#ident(#ty)));
+    quote! {
+        #bridge! {
+            (#config)
+            { #(#variants),* }
+            { #(#conversions),* }
+        }
+    }
+}
+
+/// The input to `__extend!` - a hand-written enum, and the variants to add to it.
+pub struct Extension {
+    item: ItemEnum,
+    config: Config,
+    variants: Punctuated<Variant, Token![,]>,
+    conversions: Vec<(Ident, Type)>,
+}
+
+impl Parse for Extension {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let item = input.parse()?;
+        let (config, variants, conversions);
+        parenthesized!(config in input);
+        braced!(variants in input);
+        braced!(conversions in input);
+        let conversions = Punctuated::<Variant, Token![,]>::parse_terminated(&conversions)?
+            .into_iter()
+            .map(|variant| {
+                let ty = match variant.fields {
+                    Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                        fields.unnamed.into_iter().next().unwrap().ty
+                    }
+                    _ => return Err(syn::Error::new_spanned(variant.ident, "expected one field")),
+                };
+                Ok((variant.ident, ty))
+            })
+            .collect::<syn::Result<_>>()?;
+        Ok(Self {
+            item,
+            config: config.parse()?,
+            variants: Punctuated::parse_terminated(&variants)?,
+            conversions,
+        })
+    }
+}
+
+/// Define the enum from an [`Extension`], with its hand-written variants first.
+pub fn expand(extension: Extension) -> TokenStream {
+    let Extension {
+        item,
+        config,
+        variants: added,
+        conversions,
+    } = extension;
+    let mut variants = item.variants.into_iter().collect::<Vec<_>>();
+    for variant in added {
+        if let Some(written) = variants.iter().find(|it| it.ident == variant.ident) {
+            emit_error!(
+                variant.ident,
+                "`{}` is already a variant of `{}` - choose another name",
+                variant.ident,
+                item.ident
+            );
+            emit_error!(written.ident, "`{}` is written here", written.ident);
+            continue;
+        }
+        variants.push(variant)
+    }
+    crate::assemble(
+        &config,
+        &item.ident,
+        &item.vis,
+        &item.generics,
+        &item.attrs,
+        variants,
+        &conversions,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use syn::parse_quote;
+
+    #[test]
+    fn round_trip() {
+        let item: ItemEnum = parse_quote! {
+            #[derive(Debug)]
+            pub enum DbError {
+                Timeout,
+            }
+        };
+        let config = Config {
+            display: true,
+            extend: true,
+            ..Default::default()
+        };
+        let variants: [Variant; 2] = [
+            parse_quote!(
+                #[display("conflict")]
+                Conflict { id: u64 }
+            ),
+            parse_quote!(Io(io::Error)),
+        ];
+        let conversions = [(crate::ident("Io"), parse_quote!(io::Error))];
+        let bridged = bridge(
+            &config,
+            &crate::ident("DbError"),
+            &crate::ident("query"),
+            &variants,
+            &conversions,
+        );
+        let expected = quote! {
+            __errgo_extend_DbError! {
+                (display, extend)
+                { #[display("conflict")] Conflict { id: u64 }, Io(io::Error) }
+                { Io(io::Error) }
+            }
+        };
+        assert_eq!(expected.to_string(), bridged.to_string());
+
+        // What the bridge passes on to `__extend!`
+        let syn::Macro { tokens, .. } = syn::parse2(bridged).unwrap();
+        let extension: Extension = syn::parse2(quote!(#item #tokens)).unwrap();
+        assert_eq!(item, extension.item);
+        assert_eq!(config, extension.config);
+        assert_eq!(
+            variants.to_vec(),
+            extension.variants.into_iter().collect::<Vec<_>>()
+        );
+        assert_eq!(conversions.to_vec(), extension.conversions);
+    }
+
+    #[test]
+    fn claim_once() {
+        let (name, query) = (crate::ident("ClaimedError"), crate::ident("query"));
+        assert_eq!(None, claim(&name, &query));
+        // Expanding the same function again is fine
+        assert_eq!(None, claim(&name, &query));
+        assert_eq!(
+            Some("query".to_owned()),
+            claim(&name, &crate::ident("again"))
+        );
+        assert_eq!(
+            None,
+            claim(&crate::ident("OtherError"), &crate::ident("again"))
+        );
+    }
+}
@@ -0,0 +1,373 @@
+use std::collections::HashSet;
+
+use syn::{
+    visit::{self, Visit},
+    GenericArgument, GenericParam, Generics, Ident, Lifetime, Path, PathArguments, Type,
+    TypeImplTrait, TypePath, TypeReference, Variant, WhereClause,
+};
+
+/// Generic parameters (and other single identifiers) and lifetimes mentioned by some syntax.
+#[derive(Debug, Default)]
+struct Mentions {
+    idents: HashSet<Ident>,
+    lifetimes: HashSet<Lifetime>,
+}
+
+impl Mentions {
+    fn of(f: impl FnOnce(&mut Self)) -> Self {
+        let mut this = Self::default();
+        f(&mut this);
+        this
+    }
+    fn mentions(&self, param: &GenericParam) -> bool {
+        match param {
+            GenericParam::Lifetime(it) => self.lifetimes.contains(&it.lifetime),
+            GenericParam::Type(it) => self.idents.contains(&it.ident),
+            GenericParam::Const(it) => self.idents.contains(&it.ident),
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for Mentions {
+    fn visit_lifetime(&mut self, i: &'ast Lifetime) {
+        self.lifetimes.insert(i.clone());
+    }
+    fn visit_path(&mut self, i: &'ast Path) {
+        if let (None, Some(first)) = (i.leading_colon, i.segments.first()) {
+            self.idents.insert(first.ident.clone());
+        }
+        visit::visit_path(self, i)
+    }
+}
+
+/// Build the generics for an error written like `ParseError<'a, T>` in the return type,
+/// copying each parameter (and any relevant `where` clauses) from those in `scope`.
+pub fn carry(arguments: &PathArguments, scope: &Generics) -> syn::Result<Generics> {
+    let args = match arguments {
+        PathArguments::None => return Ok(Generics::default()),
+        PathArguments::AngleBracketed(it) => &it.args,
+        PathArguments::Parenthesized(it) => {
+            return Err(syn::Error::new_spanned(
+                it,
+                "unsupported error type - expected generic arguments like `<'a, T>`",
+            ))
+        }
+    };
+
+    let mut generics = Generics::default();
+    for arg in args {
+        let found = match arg {
+            GenericArgument::Lifetime(lifetime) => scope.params.iter().find(
+                |param| matches!(param, GenericParam::Lifetime(it) if it.lifetime == *lifetime),
+            ),
+            GenericArgument::Type(Type::Path(TypePath { qself: None, path })) => {
+                match path.get_ident() {
+                    Some(ident) => scope.params.iter().find(|param| match param {
+                        GenericParam::Type(it) => it.ident == *ident,
+                        GenericParam::Const(it) => it.ident == *ident,
+                        GenericParam::Lifetime(_) => false,
+                    }),
+                    None => None,
+                }
+            }
+            _ => None,
+        };
+        let Some(found) = found else {
+            return Err(syn::Error::new_spanned(
+                arg,
+                "unsupported generic argument - must be a generic parameter of the function",
+            ));
+        };
+        let mut param = found.clone();
+        match &mut param {
+            GenericParam::Type(it) => {
+                it.eq_token = None;
+                it.default = None;
+            }
+            GenericParam::Const(it) => {
+                it.eq_token = None;
+                it.default = None;
+            }
+            GenericParam::Lifetime(_) => {}
+        }
+        generics.params.push(param);
+    }
+    generics.lt_token = Some(Default::default());
+    generics.gt_token = Some(Default::default());
+    carry_bounds(&mut generics, scope);
+    Ok(generics)
+}
+
+/// The name of a generic parameter, to compare them regardless of their bounds and defaults.
+fn param_name(param: &GenericParam) -> &Ident {
+    match param {
+        GenericParam::Lifetime(it) => &it.lifetime.ident,
+        GenericParam::Type(it) => &it.ident,
+        GenericParam::Const(it) => &it.ident,
+    }
+}
+
+/// Only keep the bounds on `generics`, and the `where` clauses in `scope`, which don't mention parameters left out of `generics`.
+fn carry_bounds(generics: &mut Generics, scope: &Generics) {
+    let left_behind = scope
+        .params
+        .iter()
+        .filter(|param| {
+            !generics
+                .params
+                .iter()
+                .any(|carried| param_name(carried) == param_name(param))
+        })
+        .collect::<Vec<_>>();
+    let keep = |mentions: Mentions| !left_behind.iter().any(|param| mentions.mentions(param));
+    for param in generics.params.iter_mut() {
+        match param {
+            GenericParam::Type(it) => {
+                it.bounds = it
+                    .bounds
+                    .iter()
+                    .filter(|bound| keep(Mentions::of(|it| it.visit_type_param_bound(bound))))
+                    .cloned()
+                    .collect();
+                if it.bounds.is_empty() {
+                    it.colon_token = None
+                }
+            }
+            GenericParam::Lifetime(it) => {
+                it.bounds = it
+                    .bounds
+                    .iter()
+                    .filter(|bound| keep(Mentions::of(|it| it.visit_lifetime(bound))))
+                    .cloned()
+                    .collect();
+                if it.bounds.is_empty() {
+                    it.colon_token = None
+                }
+            }
+            GenericParam::Const(_) => {}
+        }
+    }
+    if let Some(where_clause) = &scope.where_clause {
+        let predicates = where_clause
+            .predicates
+            .iter()
+            .filter(|predicate| keep(Mentions::of(|it| it.visit_where_predicate(predicate))))
+            .cloned()
+            .collect::<syn::punctuated::Punctuated<_, _>>();
+        if !predicates.is_empty() {
+            generics.where_clause = Some(WhereClause {
+                where_token: where_clause.where_token,
+                predicates,
+            })
+        }
+    }
+}
+
+/// The parameters in `generics` which are used by `variant`, along with any relevant `where` clauses.
+pub fn subset(generics: &Generics, variant: &Variant) -> Generics {
+    let unused = unused(generics, std::slice::from_ref(variant));
+    let mut subset = Generics {
+        params: generics
+            .params
+            .iter()
+            .filter(|param| !unused.contains(param))
+            .cloned()
+            .collect(),
+        ..Default::default()
+    };
+    if !subset.params.is_empty() {
+        subset.lt_token = Some(Default::default());
+        subset.gt_token = Some(Default::default());
+    }
+    carry_bounds(&mut subset, generics);
+    subset
+}
+
+/// Parameters in `generics` which aren't used by any of the `variants`.
+pub fn unused<'a>(generics: &'a Generics, variants: &[Variant]) -> Vec<&'a GenericParam> {
+    let mentions = Mentions::of(|it| {
+        for variant in variants {
+            it.visit_fields(&variant.fields)
+        }
+    });
+    generics
+        .params
+        .iter()
+        .filter(|param| !mentions.mentions(param))
+        .collect()
+}
+
+/// Why `ty`, the type of a function parameter, can't be written elsewhere - like `&str` with its elided lifetime.
+pub fn unnameable(ty: &Type) -> Option<&'static str> {
+    #[derive(Default)]
+    struct Unnameable(Option<&'static str>);
+    impl<'ast> Visit<'ast> for Unnameable {
+        fn visit_type_reference(&mut self, i: &'ast TypeReference) {
+            if i.lifetime.is_none() {
+                self.0 = Some("an elided lifetime")
+            }
+            visit::visit_type_reference(self, i)
+        }
+        fn visit_lifetime(&mut self, i: &'ast Lifetime) {
+            if i.ident == "_" {
+                self.0 = Some("an elided lifetime")
+            }
+        }
+        fn visit_type_impl_trait(&mut self, _: &'ast TypeImplTrait) {
+            self.0 = Some("`impl Trait`")
+        }
+    }
+    let mut visitor = Unnameable::default();
+    visitor.visit_type(ty);
+    visitor.0
+}
+
+/// All the generics in scope for a method, from both the `impl` block and the method itself.
+pub fn merge(outer: &Generics, inner: &Generics) -> Generics {
+    let mut merged = outer.clone();
+    merged.params.extend(inner.params.iter().cloned());
+    if let Some(inner) = &inner.where_clause {
+        merged
+            .make_where_clause()
+            .predicates
+            .extend(inner.predicates.iter().cloned());
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use quote::quote;
+    use syn::{parse2, ItemFn};
+
+    fn carry_from(function: proc_macro2::TokenStream, error: proc_macro2::TokenStream) -> Generics {
+        let function = parse2::<ItemFn>(function).unwrap();
+        let error = parse2::<Path>(error).unwrap();
+        carry(
+            &error.segments.last().unwrap().arguments,
+            &function.sig.generics,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn carry_params() {
+        let generics = carry_from(
+            quote! {
+                fn foo<'a, 'b, T: Debug, U, const N: usize>()
+                where
+                    T: 'a,
+                    U: Clone,
+                    'a: 'b
+                {}
+            },
+            quote!(ParseError<'a, N, T>),
+        );
+        let expected = parse2::<ItemFn>(quote! {
+            fn expected<'a, const N: usize, T: Debug>() where T: 'a {}
+        })
+        .unwrap()
+        .sig
+        .generics;
+        assert_eq!(expected, generics);
+    }
+
+    #[test]
+    fn carry_inline_bounds() {
+        let generics = carry_from(
+            quote!(
+                fn foo<'a: 'b, 'b, T, U: Into<T> + Debug>() {}
+            ),
+            quote!(ParseError<'a, U>),
+        );
+        let expected = parse2::<ItemFn>(quote!(
+            fn expected<'a, U: Debug>() {}
+        ))
+        .unwrap()
+        .sig
+        .generics;
+        assert_eq!(expected, generics);
+    }
+
+    #[test]
+    fn carry_nothing() {
+        assert_eq!(
+            Generics::default(),
+            carry_from(
+                quote!(
+                    fn foo<T>() {}
+                ),
+                quote!(ParseError)
+            )
+        );
+    }
+
+    #[test]
+    fn carry_unknown() {
+        let function = parse2::<ItemFn>(quote!(
+            fn foo<T>() {}
+        ))
+        .unwrap();
+        for error in [quote!(ParseError<U>), quote!(ParseError<Vec<T>>)] {
+            let error = parse2::<Path>(error).unwrap();
+            assert!(carry(&error.segments[0].arguments, &function.sig.generics).is_err());
+        }
+    }
+
+    #[test]
+    fn find_unnameable() {
+        for (ty, expected) in [
+            (quote!(usize), None),
+            (quote!(&'a str), None),
+            (quote!(Vec<&'static str>), None),
+            (quote!(&str), Some("an elided lifetime")),
+            (quote!(Cow<'_, str>), Some("an elided lifetime")),
+            (quote!(Option<&mut [u8]>), Some("an elided lifetime")),
+            (quote!(impl Display), Some("`impl Trait`")),
+        ] {
+            assert_eq!(expected, unnameable(&parse2(ty).unwrap()));
+        }
+    }
+
+    #[test]
+    fn find_unused() {
+        let generics = carry_from(
+            quote!(
+                fn foo<'a, 'b, T, U>() {}
+            ),
+            quote!(ParseError<'a, 'b, T, U>),
+        );
+        let variants = [
+            parse2::<Variant>(quote!(Unexpected { token: &'a str })).unwrap(),
+            parse2::<Variant>(quote!(Payload(Vec<T>))).unwrap(),
+        ];
+        let unused = unused(&generics, &variants)
+            .into_iter()
+            .map(|param| quote!(#param).to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["'b", "U"], unused);
+    }
+
+    #[test]
+    fn subset_for_variant() {
+        let generics = carry_from(
+            quote! {
+                fn foo<'a, T: Debug, U>() where T: 'a, U: Clone {}
+            },
+            quote!(ParseError<'a, T, U>),
+        );
+        let variant = parse2::<Variant>(quote!(Payload { got: &'a [T] })).unwrap();
+        let expected = parse2::<ItemFn>(quote! {
+            fn expected<'a, T: Debug>() where T: 'a {}
+        })
+        .unwrap()
+        .sig
+        .generics;
+        assert_eq!(expected, subset(&generics, &variant));
+
+        let variant = parse2::<Variant>(quote!(Empty)).unwrap();
+        assert_eq!(Generics::default(), subset(&generics, &variant));
+    }
+}
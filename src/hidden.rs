@@ -0,0 +1,303 @@
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote, quote_spanned, ToTokens};
+use syn::{Expr, Field, Fields, Generics, Ident, Path, Type, TypePath, Variant, Visibility};
+
+use crate::{config::Config, structs::Layout};
+
+/// A field which errgo adds to the end of every variant, like the location of the `err!` which created it.
+///
+/// Its type is a wrapper which compares equal to every other, so it doesn't affect derived `PartialEq`, `Hash` etc.
+#[derive(Debug, Clone)]
+pub struct Hidden {
+    kind: Kind,
+    pub ident: Ident,
+    pub ty: Type,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Location,
+    Backtrace,
+}
+
+impl Hidden {
+    /// The hidden fields requested by `config`, for the error named `error_name`.
+    pub fn all(config: &Config, error_name: &Ident) -> Vec<Self> {
+        let mut all = Vec::new();
+        if config.location {
+            all.push(Self::new(Kind::Location, error_name))
+        }
+        if config.backtrace {
+            all.push(Self::new(Kind::Backtrace, error_name))
+        }
+        all
+    }
+    fn new(kind: Kind, error_name: &Ident) -> Self {
+        let (name, wrapper) = match kind {
+            Kind::Location => ("location", "Location"),
+            Kind::Backtrace => ("backtrace", "Backtrace"),
+        };
+        let wrapper = format_ident!("__{}{}", error_name, wrapper);
+        Self {
+            kind,
+            ident: format_ident!("__{}", name),
+            ty: Type::Path(TypePath {
+                qself: None,
+                path: wrapper.into(),
+            }),
+        }
+    }
+    /// The value of this field for an error created at `span`.
+    pub fn value(&self, span: Span) -> Expr {
+        self.value_of(&self.ty, span)
+    }
+    /// [`Self::value`], for an error defined in `module` rather than alongside the function creating it.
+    pub fn value_in(&self, module: &Path, span: Span) -> Expr {
+        let Type::Path(TypePath { path, .. }) = &self.ty else {
+            unreachable!("hidden fields have a path type")
+        };
+        let mut qualified = module.clone();
+        qualified.segments.extend(path.segments.iter().cloned());
+        self.value_of(&qualified, span)
+    }
+    fn value_of(&self, ty: &impl ToTokens, span: Span) -> Expr {
+        let value = match self.kind {
+            Kind::Location => quote_spanned!(span=> ::core::panic::Location::caller()),
+            Kind::Backtrace => quote_spanned! {span=>
+                ::std::sync::Arc::new(::std::backtrace::Backtrace::capture())
+            },
+        };
+        syn::parse_quote_spanned!(span=> #ty(#value))
+    }
+    pub fn is(&self, field: &Field) -> bool {
+        field.ty == self.ty
+    }
+    /// The definition of the wrapper type.
+    fn define(&self, vis: &Visibility) -> TokenStream {
+        let ty = &self.ty;
+        let (inner, derives) = match self.kind {
+            Kind::Location => (
+                quote!(&'static ::core::panic::Location<'static>),
+                quote!(#[derive(Clone, Copy)]),
+            ),
+            // Shared, since `Backtrace` isn't `Clone`
+            Kind::Backtrace => (
+                quote!(::std::sync::Arc<::std::backtrace::Backtrace>),
+                quote!(#[derive(Clone)]),
+            ),
+        };
+        quote! {
+            #[doc(hidden)]
+            #[allow(non_camel_case_types)]
+            #derives
+            #vis struct #ty(pub #inner);
+
+            impl ::core::fmt::Debug for #ty {
+                fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                    ::core::fmt::Debug::fmt(&self.0, f)
+                }
+            }
+            impl ::core::cmp::PartialEq for #ty {
+                fn eq(&self, _: &Self) -> bool {
+                    true
+                }
+            }
+            impl ::core::cmp::Eq for #ty {}
+            impl ::core::cmp::PartialOrd for #ty {
+                fn partial_cmp(&self, other: &Self) -> ::core::option::Option<::core::cmp::Ordering> {
+                    ::core::option::Option::Some(::core::cmp::Ord::cmp(self, other))
+                }
+            }
+            impl ::core::cmp::Ord for #ty {
+                fn cmp(&self, _: &Self) -> ::core::cmp::Ordering {
+                    ::core::cmp::Ordering::Equal
+                }
+            }
+            impl ::core::hash::Hash for #ty {
+                fn hash<H: ::core::hash::Hasher>(&self, _: &mut H) {}
+            }
+        }
+    }
+    /// A method on the generated enum which gets this field from any variant, reached through `layout`.
+    fn accessor(&self, vis: &Visibility, variants: &[Variant], layout: Layout<'_>) -> TokenStream {
+        let binding = &self.ident;
+        let (method, output, access, docs) = match self.kind {
+            Kind::Location => (
+                format_ident!("location"),
+                quote!(&'static ::core::panic::Location<'static>),
+                quote!(#binding.0),
+                "Where this error was created.",
+            ),
+            Kind::Backtrace => (
+                format_ident!("backtrace"),
+                quote!(&::std::backtrace::Backtrace),
+                quote!(&#binding.0),
+                "The backtrace captured when this error was created.",
+            ),
+        };
+        let arms = variants.iter().map(|variant| {
+            let ident = &variant.ident;
+            let pattern = match &variant.fields {
+                Fields::Named(_) => layout.path(ident, quote!({ #binding, .. })),
+                Fields::Unnamed(fields) => {
+                    let bindings = fields.unnamed.iter().map(|field| match self.is(field) {
+                        true => quote!(#binding),
+                        false => quote!(_),
+                    });
+                    layout.path(ident, quote!((#(#bindings),*)))
+                }
+                Fields::Unit => unreachable!("hidden fields are added to every variant"),
+            };
+            quote!(#pattern => #access)
+        });
+        let body = match variants.is_empty() {
+            true => quote!(match *self {}),
+            false => quote!(match self { #(#arms,)* }),
+        };
+        quote! {
+            #[doc = #docs]
+            #vis fn #method(&self) -> #output {
+                #body
+            }
+        }
+    }
+}
+
+/// Provide the backtrace through the `Error::provide` API, which is unstable.
+///
+/// The generated code only compiles on nightly, with `#![feature(error_generic_member_access)]`.
+pub fn provide() -> TokenStream {
+    quote! {
+        fn provide<'__request>(&'__request self, request: &mut ::core::error::Request<'__request>) {
+            request.provide_ref::<::std::backtrace::Backtrace>(self.backtrace());
+        }
+    }
+}
+
+pub fn is_hidden(field: &Field, hidden: &[Hidden]) -> bool {
+    hidden.iter().any(|it| it.is(field))
+}
+
+/// Define the wrapper types for `hidden`, and implement their accessors on the generated enum.
+pub fn implement(
+    name: &Ident,
+    vis: &Visibility,
+    generics: &Generics,
+    hidden: &[Hidden],
+    variants: &[Variant],
+    layout: Layout<'_>,
+) -> TokenStream {
+    if hidden.is_empty() {
+        return quote!();
+    }
+    let definitions = hidden.iter().map(|it| it.define(vis));
+    let accessors = accessors(name, vis, generics, hidden, variants, layout);
+    quote! {
+        #(#definitions)*
+
+        #accessors
+    }
+}
+
+/// Implement the accessors for `hidden` on the type called `name`, whose fields are those of `variants`.
+pub fn accessors(
+    name: &Ident,
+    vis: &Visibility,
+    generics: &Generics,
+    hidden: &[Hidden],
+    variants: &[Variant],
+    layout: Layout<'_>,
+) -> TokenStream {
+    if hidden.is_empty() {
+        return quote!();
+    }
+    let accessors = hidden.iter().map(|it| it.accessor(vis, variants, layout));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            #(#accessors)*
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ident;
+    use pretty_assertions::assert_eq;
+    use syn::parse_quote;
+
+    #[test]
+    fn location_accessor() {
+        let config = Config {
+            location: true,
+            ..Default::default()
+        };
+        let hidden = Hidden::all(&config, &ident("FooError"));
+        let variants: [Variant; 2] = [
+            parse_quote!(Timeout {
+                __location: __FooErrorLocation
+            }),
+            parse_quote!(Code(i32, __FooErrorLocation)),
+        ];
+        let expected = quote! {
+            #[doc = "Where this error was created."]
+            pub fn location(&self) -> &'static ::core::panic::Location<'static> {
+                match self {
+                    Self::Timeout { __location, .. } => __location.0,
+                    Self::Code(_, __location) => __location.0,
+                }
+            }
+        };
+        assert_eq!(
+            expected.to_string(),
+            hidden[0]
+                .accessor(&parse_quote!(pub), &variants, Layout::Inline)
+                .to_string()
+        );
+        assert!(!is_hidden(
+            variants[1].fields.iter().next().unwrap(),
+            &hidden
+        ));
+        assert!(is_hidden(
+            variants[0].fields.iter().next().unwrap(),
+            &hidden
+        ));
+    }
+
+    #[test]
+    fn backtrace_accessor() {
+        let config = Config {
+            location: true,
+            backtrace: true,
+            ..Default::default()
+        };
+        let hidden = Hidden::all(&config, &ident("FooError"));
+        let variants: [Variant; 2] = [
+            parse_quote!(Timeout {
+                __location: __FooErrorLocation,
+                __backtrace: __FooErrorBacktrace
+            }),
+            parse_quote!(Code(i32, __FooErrorLocation, __FooErrorBacktrace)),
+        ];
+        let expected = quote! {
+            #[doc = "The backtrace captured when this error was created."]
+            fn backtrace(&self) -> &::std::backtrace::Backtrace {
+                match self {
+                    Self::Timeout(foo_error::Timeout { __backtrace, .. }) => &__backtrace.0,
+                    Self::Code(foo_error::Code(_, _, __backtrace)) => &__backtrace.0,
+                }
+            }
+        };
+        assert_eq!(
+            expected.to_string(),
+            hidden[1]
+                .accessor(
+                    &Visibility::Inherited,
+                    &variants,
+                    Layout::Wrapped(&ident("foo_error"))
+                )
+                .to_string()
+        );
+    }
+}
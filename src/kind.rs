@@ -0,0 +1,103 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{Fields, Generics, Ident, Variant, Visibility};
+
+use crate::structs::Layout;
+
+/// The name of the kind enum for the error called `name`, like `ShaveYaksErrorKind`.
+fn kind_name(name: &Ident) -> Ident {
+    format_ident!("{}Kind", name)
+}
+
+/// Define a fieldless enum with a unit variant for each of `variants`, and implement `kind()` on the generated enum to get it.
+///
+/// It's `#[non_exhaustive]`, so that adding a variant isn't a breaking change for other crates.
+pub fn implement(
+    name: &Ident,
+    vis: &Visibility,
+    generics: &Generics,
+    variants: &[Variant],
+    layout: Layout<'_>,
+) -> TokenStream {
+    let kind = kind_name(name);
+    let idents = variants.iter().map(|it| &it.ident).collect::<Vec<_>>();
+    let arms = variants.iter().map(|variant| {
+        let ident = &variant.ident;
+        let pattern = match &variant.fields {
+            Fields::Named(_) => layout.path(ident, quote!({ .. })),
+            Fields::Unnamed(_) => layout.path(ident, quote!((..))),
+            Fields::Unit => layout.path(ident, quote!()),
+        };
+        quote!(#pattern => #kind::#ident)
+    });
+    let body = match variants.is_empty() {
+        true => quote!(match *self {}),
+        false => quote!(match self { #(#arms,)* }),
+    };
+    let docs = format!("The kind of a [`{name}`], without its fields.");
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    quote! {
+        #[doc = #docs]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        #[non_exhaustive]
+        #vis enum #kind {
+            #(#idents),*
+        }
+
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// The kind of this error.
+            #vis fn kind(&self) -> #kind {
+                #body
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ident;
+    use pretty_assertions::assert_eq;
+    use syn::parse_quote;
+
+    #[test]
+    fn implement_kind() {
+        let variants: [Variant; 3] = [
+            parse_quote!(NoYaks),
+            parse_quote!(NotEnoughBuckets { got: &'a usize }),
+            parse_quote!(Io(io::Error)),
+        ];
+        let expected = quote! {
+            #[doc = "The kind of a [`ShaveYaksError`], without its fields."]
+            #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+            #[non_exhaustive]
+            pub enum ShaveYaksErrorKind {
+                NoYaks,
+                NotEnoughBuckets,
+                Io
+            }
+
+            impl<'a> ShaveYaksError<'a> {
+                /// The kind of this error.
+                pub fn kind(&self) -> ShaveYaksErrorKind {
+                    match self {
+                        Self::NoYaks => ShaveYaksErrorKind::NoYaks,
+                        Self::NotEnoughBuckets { .. } => ShaveYaksErrorKind::NotEnoughBuckets,
+                        Self::Io(..) => ShaveYaksErrorKind::Io,
+                    }
+                }
+            }
+        };
+        assert_eq!(
+            expected.to_string(),
+            implement(
+                &ident("ShaveYaksError"),
+                &parse_quote!(pub),
+                &parse_quote!(<'a>),
+                &variants,
+                Layout::Inline
+            )
+            .to_string()
+        );
+    }
+}
@@ -5,7 +5,10 @@
 //!
 //! This crate was written to aid wrapping C APIs - transforming e.g error codes to handleable messages.
 //! It shouldn't really be used for library api entry points - a well-considered top-level error type is likely to be both more readable and forward compatible.
+//! [`parent!`] can declare such a type, wrapping the errors of individual functions,
+//! and [`macro@extendable`] lets errgo add ad-hoc variants to a hand-written one.
 //! Consider reading [Study of `std::io::Error`](https://matklad.github.io/2020/10/15/study-of-std-io-error.html) or simply making all generated structs `pub(crate)`.
+//! Otherwise `#[errgo(kind)]` generates a `#[non_exhaustive]` kind enum, like [`std::io::ErrorKind`], which callers can match on as the variants change.
 //!
 //! ```
 //! use errgo::errgo;
@@ -21,7 +24,7 @@
 //!     }
 //!     if num_yaks > empty_buckets {
 //!         return Err(err!(NotEnoughBuckets {
-//!             got: usize = empty_buckets,
+//!             got = empty_buckets, // the type is taken from the parameter
 //!             required: usize = num_yaks,
 //!         }));
 //!     }
@@ -38,8 +41,42 @@
 //!     }
 //! }
 //! ```
-//! Note that the struct definition is placed just above the function body, meaning that you can't use [`macro@errgo`] on functions in `impl` blocks - you'll have to move the function body to an outer scope, and call it in the impl block.
+//! Note that the struct definition is placed just above the function body.
+//! To use [`macro@errgo`] on methods, annotate the `impl` block as well, and the generated structs will be placed just above it:
+//! ```
+//! # use errgo::errgo;
+//! struct Yak;
+//!
+//! #[errgo]
+//! impl Yak {
+//!     #[errgo]
+//!     fn shave(&self, num_razors: usize) -> Result<(), ShaveError> {
+//!         if num_razors == 0 {
+//!             return Err(err!(NotEnoughRazors));
+//!         }
+//!         Ok(())
+//!     }
+//! }
+//! ```
+//!
+//! Related functions can share one error by placing [`macro@errgo`] on their module, naming the error with `error`.
+//! Every function in the module contributes its variants, and the generated struct is placed at the top of the module:
+//! ```
+//! # use errgo::errgo;
+//! #[errgo(error = LibError)]
+//! mod lib {
+//!     pub fn open(path: &str) -> Result<(), LibError> {
+//!         if path.is_empty() {
+//!             return Err(err!(InvalidArg));
+//!         }
+//!         Ok(())
+//!     }
 //!
+//!     pub fn close(code: i32) -> Result<(), LibError> {
+//!         Err(err!(Failed(i32 = code)))
+//!     }
+//! }
+//! ```
 //!
 //! Importantly, you can derive on the generated struct, _and_ passthrough attributes, allowing you to use crates like [thiserror] or [strum].
 //! See the [`macro@errgo`] documentation for other arguments accepted by the macro.
@@ -86,15 +123,87 @@
 //! ```
 //! And `err!` macro invocations are replaced with struct instantiations - no matter where they are in the function body!
 //!
-//! If you need to reuse the same variant within a function, just use the normal construction syntax:
+//! If you'd rather not depend on [thiserror], `#[errgo(display)]` implements [`Display`](std::fmt::Display) and [`Error`](std::error::Error) itself.
+//! Messages follow the variant, or are given in a `#[display(..)]` attribute.
+//! Fields are in scope by name (or as `_0`, `_1`... for tuple variants), so interpolation is checked at compile time.
+//! Arguments after a message must be fields too, or parameters of the function - which are added as fields.
+//! ```
+//! # use errgo::errgo;
+//! #[errgo(display, derive(Debug))]
+//! fn shave_yaks(num_yaks: usize, empty_buckets: usize) -> Result<(), ShaveYaksError> {
+//!     if num_yaks > empty_buckets {
+//!         return Err(err!(
+//!             NotEnoughBuckets {
+//!                 got: usize = empty_buckets,
+//!                 required: usize = num_yaks,
+//!             },
+//!             "not enough buckets - needed {required}"
+//!         ));
+//!     }
+//!     Err(err!(#[display("{0} yaks are too many")] TooManyYaks(usize = num_yaks)))
+//! }
+//! ```
+//!
+//! If you need to reuse the same variant within a function, just repeat it - identical definitions are merged.
+//! Attributes need only be written on one of them.
 //! ```
 //! # use errgo::errgo;
 //! # use std::io;
 //! # fn fallible_op() -> Result<(), io::Error> { todo!() }
-//! #[errgo]
+//! #[errgo(derive(Debug, thiserror::Error))]
 //! fn foo() -> Result<(), FooError> {
-//!     fallible_op().map_err(|e| err!(IoError(io::Error = e)));
-//!     Err(FooError::IoError(todo!()))
+//!     fallible_op().map_err(|e| err!(#[error("io error")] IoError(io::Error = e)))?;
+//!     fallible_op().map_err(|e| err!(IoError(io::Error = e)))?;
+//!     Ok(())
+//! }
+//! ```
+//! Definitions which disagree on their fields or attributes are an error.
+//!
+//! Wrapping another error is common enough to have a shorthand - `try_err!(IoError(io::Error), op())` is `op().map_err(FooError::IoError)`.
+//! A [`From`] implementation is generated for the wrapped type too, so a plain `?` works once the variant has been declared.
+//! ```
+//! # use errgo::errgo;
+//! # use std::io;
+//! # fn fallible_op() -> Result<(), io::Error> { todo!() }
+//! #[errgo(derive(Debug, thiserror::Error))]
+//! fn foo() -> Result<(), FooError> {
+//!     try_err!(#[error("io error")] IoError(io::Error), fallible_op())?;
+//!     try_err!(IoError, fallible_op())?;
+//!     fallible_op()?;
+//!     Ok(())
+//! }
+//! ```
+//! `try_err!` evaluates to a `Result`, so in a function returning something like `Option<Result<..>>`, it must be returned by hand rather than with `?`.
+//! Each type may only be wrapped by one variant.
+//! Types are compared as written, so `io::Error` and `std::io::Error` count as different types,
+//! and the compiler reports their conflicting `From` implementations instead.
+//!
+//! For C functions which return a negative number and set `errno` on failure, `check!(libc::close(fd), Close)` returns a variant like
+//! `Close { call: &'static str, errno: i32 }` from [`std::io::Error::last_os_error`], and otherwise evaluates to the return value.
+//! It returns through any wrapper around the function's `Result`, like `Some(Err(..))` from a function returning `Option<Result<..>>`.
+//! ```
+//! # use errgo::errgo;
+//! # mod libc { pub unsafe fn close(_: i32) -> i32 { 0 } }
+//! #[errgo]
+//! fn close(fd: i32) -> Result<(), CloseError> {
+//!     unsafe { check!(libc::close(fd), Close) };
+//!     Ok(())
+//! }
+//! ```
+//!
+//! Once a variant has been declared with types, other uses may leave them out, anywhere in the function:
+//! ```
+//! # use errgo::errgo;
+//! # use std::path::PathBuf;
+//! #[errgo]
+//! fn foo(first: PathBuf, second: PathBuf) -> Result<(), FooError> {
+//!     if !second.exists() {
+//!         return Err(err!(NotFound { path: second }));
+//!     }
+//!     if !first.exists() {
+//!         return Err(err!(NotFound { path: PathBuf = first }));
+//!     }
+//!     Ok(())
 //! }
 //! ```
 //!
@@ -103,17 +212,37 @@
 //! [strum]: https://docs.rs/strum
 
 use config::Config;
-use data::VariantWithValue;
+use data::{Check, TryErr, VariantWithValue};
+use hidden::Hidden;
 use proc_macro2::{Ident, Span, TokenStream};
 use proc_macro_error::{emit_error, proc_macro_error};
-use quote::{quote, ToTokens};
+use quote::{format_ident, quote, ToTokens};
+use std::collections::HashSet;
+use structs::Layout;
 use syn::{
-    parse2, parse_macro_input, visit_mut::VisitMut, AngleBracketedGenericArguments,
-    GenericArgument, ItemFn, Path, PathArguments, PathSegment, ReturnType, TypePath,
+    parse2, parse_macro_input, parse_quote, punctuated::Punctuated, spanned::Spanned, visit::Visit,
+    visit_mut::VisitMut, Attribute, Block, Expr, Fields, FnArg, Generics, ImplItem, Item, ItemEnum,
+    Meta, MetaList, Pat, PatIdent, PatType, Path, PathSegment, Signature, Token, Type, Visibility,
 };
+use variants::Variants;
 
+mod accessors;
+mod case;
+mod code;
 mod config;
+mod conversions;
 mod data;
+mod define;
+mod display;
+mod extend;
+mod generics;
+mod hidden;
+mod kind;
+mod parent;
+mod return_type;
+mod source;
+mod structs;
+mod variants;
 
 /// See [module documentation](index.html) for general usage.
 ///
@@ -128,9 +257,32 @@ mod data;
 ///         u: usize = 1,
 ///         c: char = 'a',
 /// });
+/// err!(Tuply(2, 'b'));                // Types may be left out if they're given elsewhere
+/// err!(Structy { u: 2, c: 'b' });
 /// # Ok(())
 /// # }
 /// ```
+/// A field may also take its type from a parameter of the function, with `field = param`, or just `param`:
+/// ```
+/// # #[errgo::errgo]
+/// fn foo(got: usize, name: String) -> Result<(), FooError> {
+///     Err(err!(Short { len = got, name })) // Short { len: usize, name: String }
+/// }
+/// ```
+/// The value must be the parameter itself, and its type mustn't have elided lifetimes - give those types explicitly.
+/// If the variant's types are given by another `err!`, any value can be used this way, like `len = local`.
+///
+/// Like struct initialisation, `field: Type` takes its value from the local variable with the same name:
+/// ```
+/// # #[errgo::errgo]
+/// fn foo(key: &str) -> Result<(), FooError> {
+///     let id = key.len() as u64;
+///     let name = key.to_owned();
+///     Err(err!(Conflict { id: u64, name: String })) // Conflict { id, name }
+/// }
+/// ```
+/// If the variant's types are given by another `err!`, `field: value` is read as a value instead.
+/// A lowercase name other than a primitive type, like `n: count`, is always a value, as is a constant like `code: MAX_CODE`.
 /// # Arguments
 /// `derive` arguments are passed through to the generated struct.
 /// ```
@@ -139,14 +291,14 @@ mod data;
 /// # fn foo() -> Result<(), FooError> { Ok(()) }
 /// ```
 ///
-/// `attributes` arguments are passed through to the top of the generated struct
+/// `attributes` arguments are passed through to the top of the generated struct, after any `derive`.
 /// ```
 /// # use errgo::errgo;
 /// #[errgo(attributes(
 ///     #[must_use = "maybe you missed something!"]
 ///     #[repr(u8)]
 /// ))]
-/// # fn foo() -> Result<(), FooError> { Ok(()) }
+/// # fn foo() -> Result<(), FooError> { Err(err!(Bar)) }
 /// ```
 /// `visibility` can be used to override the generated struct's visibility.
 /// ```
@@ -154,6 +306,242 @@ mod data;
 /// #[errgo(visibility(pub))]
 /// # fn foo() -> Result<(), FooError> { Ok(()) }
 /// ```
+/// `error` names the generated struct explicitly, for functions which return a `Result` alias.
+/// It always takes precedence over the error in the return type.
+/// ```
+/// # use errgo::errgo;
+/// #[errgo(error = FooError, derive(Debug, thiserror::Error))]
+/// fn foo() -> Result<(), Box<dyn std::error::Error>> {
+///     Err(err!(#[error("bad")] Bad))?
+/// }
+/// ```
+/// `display` implements [`Display`](std::fmt::Display) and [`Error`](std::error::Error) for the generated struct, using each variant's message.
+/// Without it, messages are passed through as `#[display(..)]` attributes, for use with other derives.
+/// ```
+/// # use errgo::errgo;
+/// #[errgo(display, derive(Debug))]
+/// # fn foo() -> Result<(), FooError> { Err(err!(Bar, "bar")) }
+/// ```
+/// `default_display` gives variants without a message one built from their name and fields,
+/// so `NotEnoughBuckets { got, required }` displays as "not enough buckets (got: 3, required: 5)".
+/// Primitives, `str` and `String` are formatted with `Display`, and other fields with `Debug`.
+/// It works with `display`, or adds `#[error(..)]` attributes when deriving thiserror's `Error`.
+/// ```
+/// # use errgo::errgo;
+/// #[errgo(default_display, derive(Debug, thiserror::Error))]
+/// # fn foo() -> Result<(), FooError> { Err(err!(Bar)) }
+/// ```
+/// In `display` mode, `Error::source` returns the field marked `#[source]` or `#[from]`, or named `source`.
+/// The marker is removed, so `#[from]` doesn't implement `From` as it would with thiserror.
+/// `source_by_type` also treats the first field whose type ends in `Error` as the source,
+/// adding `#[source]` for thiserror if `display` isn't set.
+/// ```
+/// # use errgo::errgo;
+/// # use std::io;
+/// #[errgo(display, source_by_type, derive(Debug))]
+/// fn foo(e: io::Error) -> Result<(), FooError> {
+///     Err(err!(Io(io::Error = e), "couldn't foo"))
+/// }
+/// ```
+/// `location` records where each error was created, available from a generated `location()` method.
+/// It's kept in a hidden field at the end of every variant, which is ignored by comparisons and hashing.
+/// That makes unit variants into struct variants, so match them like `FooError::Bar { .. }`.
+/// ```
+/// # use errgo::errgo;
+/// #[errgo(location, derive(Debug, PartialEq))]
+/// fn foo() -> Result<(), FooError> { Err(err!(Bar)) }
+///
+/// let e = foo().unwrap_err();
+/// assert!(matches!(e, FooError::Bar { .. }));
+/// println!("failed at {}", e.location());
+/// ```
+/// `backtrace` captures a [`Backtrace`](std::backtrace::Backtrace) wherever an error is created, available from a generated `backtrace()` method.
+/// Like `location`, it's kept in a hidden field.
+/// With `display`, `backtrace(provide)` also provides it through the unstable `Error::provide` API.
+/// That's nightly-only - the crate using it needs `#![feature(error_generic_member_access)]`.
+/// ```
+/// # use errgo::errgo;
+/// #[errgo(backtrace, derive(Debug))]
+/// fn foo() -> Result<(), FooError> { Err(err!(Bar)) }
+///
+/// println!("{}", foo().unwrap_err().backtrace());
+/// ```
+/// `code` maps variants to C-style error codes, given as a `#[code = ..]` attribute or a discriminant.
+/// It generates `code(&self) -> i32` and `from_code(i32) -> Option<Self>`, with an `Unknown(i32)` variant for codes without one.
+/// Variants with fields can't be recreated from their code, so they come back as `Unknown` too.
+/// It displays as "unknown error code {0}" with `display`, or when deriving thiserror's `Error`.
+/// Declare `Unknown(i32)` yourself to give it another message, and give each variant a different code.
+/// ```
+/// # use errgo::errgo;
+/// #[errgo(code, derive(Debug, PartialEq))]
+/// fn foo(code: i32) -> Result<(), FooError> {
+///     match code {
+///         -22 => Err(err!(#[code = -22] InvalidArg)),
+///         _ => Err(err!(Busy = -16)),
+///     }
+/// }
+///
+/// assert_eq!(foo(-22).unwrap_err().code(), -22);
+/// assert_eq!(FooError::from_code(-16), Some(FooError::Busy));
+/// assert_eq!(FooError::from_code(-1), None);
+/// ```
+/// `parent` implements [`From`] for a parent error declared with [`parent!`], which wraps the generated struct.
+/// ```
+/// # use errgo::errgo;
+/// errgo::parent! {
+///     enum CrateError { FooError }
+/// }
+///
+/// #[errgo(parent = CrateError)]
+/// fn foo() -> Result<(), FooError> { Err(err!(Bad)) }
+///
+/// fn api() -> Result<(), CrateError> { Ok(foo()?) }
+/// ```
+/// `extend` adds the variants to a hand-written enum marked [`macro@extendable`] in the same module, instead of generating one.
+///
+/// `accessors` generates `is_*`, `as_*` and `into_*` methods for each variant, named for it in snake case.
+/// `as_*` and `into_*` return its fields - as a tuple if there's more than one - and are left out for variants without fields.
+/// ```
+/// # use errgo::errgo;
+/// #[errgo(accessors, derive(Debug))]
+/// fn shave_yaks(num_yaks: usize, empty_buckets: usize) -> Result<(), ShaveYaksError> {
+///     if num_yaks == 0 {
+///         return Err(err!(NoYaks));
+///     }
+///     Err(err!(NotEnoughBuckets { got = empty_buckets, required: usize = num_yaks }))
+/// }
+///
+/// let e = shave_yaks(2, 1).unwrap_err();
+/// assert!(!e.is_no_yaks());
+/// assert_eq!(e.as_not_enough_buckets(), Some((&1, &2)));
+/// assert_eq!(e.into_not_enough_buckets(), Some((1, 2)));
+/// ```
+///
+/// `kind` generates a fieldless `{Error}Kind` enum with a variant for each of the error's, like [`std::io::ErrorKind`],
+/// and a `kind()` method returning it.
+/// It's `Copy`, `Eq` and `Hash`, and `#[non_exhaustive]` so that other crates can match on it without breaking when variants are added.
+/// ```
+/// # use errgo::errgo;
+/// #[errgo(kind, derive(Debug))]
+/// fn shave_yaks(num_yaks: usize, empty_buckets: usize) -> Result<(), ShaveYaksError> {
+///     Err(err!(NotEnoughBuckets { got = empty_buckets, required: usize = num_yaks }))
+/// }
+///
+/// assert_eq!(shave_yaks(2, 1).unwrap_err().kind(), ShaveYaksErrorKind::NotEnoughBuckets);
+/// ```
+///
+/// `variant_structs` moves the fields of each variant into a struct of the same name, which the variant wraps.
+/// The structs get the same `derive`s, and convert to and from the enum, so a single failure can be returned or matched on.
+/// They're defined in a module named after the enum in snake case, so errors from different functions can have variants
+/// of the same name, and they don't clash with types of the same name that are imported.
+/// ```
+/// # use errgo::errgo;
+/// #[errgo(variant_structs, derive(Debug, Clone, PartialEq))]
+/// fn shave_yaks(num_yaks: usize, empty_buckets: usize) -> Result<(), ShaveYaksError> {
+///     if num_yaks > empty_buckets {
+///         return Err(err!(NotEnoughBuckets { got = empty_buckets, required: usize = num_yaks }));
+///     }
+///     Ok(())
+/// }
+///
+/// # fn main() {
+/// let ShaveYaksError::NotEnoughBuckets(buckets) = shave_yaks(2, 1).unwrap_err();
+/// assert_eq!(buckets, shave_yaks_error::NotEnoughBuckets { got: 1, required: 2 });
+/// assert_eq!(
+///     shave_yaks_error::NotEnoughBuckets::try_from(ShaveYaksError::from(buckets.clone())),
+///     Ok(buckets)
+/// );
+/// # }
+/// ```
+/// With `display` the structs implement `Display` and `Error` too, and with thiserror each variant becomes `#[error(transparent)]`.
+/// The module imports everything from the one the enum is in, so field types can be named as they are there,
+/// but not through a `self::` or `super::` path, nor from inside the function body -
+/// and so the enum can't be defined inside a function with `variant_structs` either.
+/// A field's type also can't share a name with any of the variants, as the struct would take its place.
+/// `attributes` are only added to the enum, and `variant_structs` can't be used with `extend`.
+///
+/// `define_in` defines the enum in another module, where it's placed by [`collect!`], and the return type is rewritten to name it there.
+/// The enum is `pub(crate)` unless the function or `visibility` says otherwise.
+///
+/// `alias` declares other types which should be treated like `Result<_, _>`.
+/// The error is taken from their second argument, unless `error` is given - which is needed when they don't have one.
+/// Only the last segment of the path is compared.
+/// ```
+/// # use errgo::errgo;
+/// type Fallible<T, E = Box<dyn std::error::Error>> = Result<T, E>;
+///
+/// #[errgo(alias = Fallible)]
+/// fn foo() -> Fallible<(), FooError> { Ok(()) }
+///
+/// #[errgo(alias = Fallible, error = BarError, derive(Debug, thiserror::Error))]
+/// fn bar() -> Fallible<()> {
+///     Err(err!(#[error("bad")] Bad))?
+/// }
+/// ```
+/// # Generics
+/// The generated struct may be generic over the function's lifetimes and type parameters, along with any relevant `where` clauses.
+/// Every parameter must be used by some variant.
+/// ```
+/// # use errgo::errgo;
+/// # use std::fmt::Debug;
+/// #[errgo(derive(Debug))]
+/// fn parse<'a, T: Debug>(input: &'a str, payload: T) -> Result<(), ParseError<'a, T>> {
+///     if input.is_empty() {
+///         return Err(err!(Payload(T = payload)));
+///     }
+///     Err(err!(Unexpected { token: &'a str = input }))
+/// }
+/// ```
+/// # Return types
+/// The error is found in a `Result<_, SomeConcreteErr>`, which may be wrapped in a `Poll`, an `Option` or a `ControlFlow`.
+/// Returning `Result<_, Box<SomeConcreteErr>>` keeps the function's stack usage small - `err!` boxes the error for you,
+/// and `?` boxes the errors from `try_err!` and `check!`.
+/// ```
+/// # use errgo::errgo;
+/// #[errgo(derive(Debug))]
+/// fn parse(input: &str) -> Result<u8, Box<ParseError>> {
+///     Err(err!(TooLong { input: [u8; 64] = [0; 64] }))
+/// }
+/// ```
+/// # Async
+/// `async fn`s work as usual, as do `err!`s inside `async` blocks.
+/// The error may also be named in a future's output, like `impl Future<Output = Result<_, FooError>>`,
+/// `Pin<Box<dyn Future<Output = Result<_, FooError>> + Send>>` or `BoxFuture<'_, Result<_, FooError>>`.
+/// ```
+/// # use errgo::errgo;
+/// # use std::future::Future;
+/// #[errgo(derive(Debug))]
+/// fn fetch(url: String) -> impl Future<Output = Result<Vec<u8>, FetchError>> + Send {
+///     async move { Err(err!(Unreachable { url: String = url })) }
+/// }
+/// ```
+/// The generated enum is `Send` and `Sync` whenever its fields are, so it can cross `.await`s on multithreaded executors.
+/// # `impl` blocks
+/// `#[errgo]` may also be placed on an `impl` block, where it expands each method annotated with `#[errgo]`.
+/// Arguments on the `impl` block are shared by every method.
+/// ```
+/// # use errgo::errgo;
+/// # struct Foo;
+/// #[errgo(derive(Debug))]
+/// impl Foo {
+///     #[errgo(visibility(pub))]
+///     fn foo(&self) -> Result<(), FooError> { Ok(()) }
+///     #[errgo]
+///     fn bar(&self) -> Result<(), BarError> { Ok(()) }
+/// }
+/// ```
+/// # Modules
+/// On an inline module, `#[errgo(error = SomeErr)]` generates a single struct from every function and method in the module,
+/// merging repeated variants.
+/// The struct is `pub` unless `visibility` is given, and functions with their own `#[errgo]` are left alone.
+/// ```
+/// # use errgo::errgo;
+/// #[errgo(error = LibError, derive(Debug))]
+/// mod lib {
+///     pub fn open() -> Result<(), LibError> { Err(err!(Busy)) }
+///     pub fn close() -> Result<(), LibError> { Err(err!(Busy)) }
+/// }
+/// ```
 #[proc_macro_attribute]
 #[proc_macro_error]
 pub fn errgo(
@@ -162,80 +550,595 @@ pub fn errgo(
 ) -> proc_macro::TokenStream {
     // Parse our inputs
     let config = parse_macro_input!(attr as Config);
-    let mut item = parse_macro_input!(item as ItemFn);
+    let item = parse_macro_input!(item as Item);
+
+    match item {
+        Item::Fn(mut item) => {
+            let generics = item.sig.generics.clone();
+            let error_enum = expand(
+                config,
+                &item.vis,
+                &mut item.sig,
+                &generics,
+                &mut item.block,
+            );
+            quote! {
+                #error_enum
 
-    let Some(error_name) = get_struct_name_from_return_type(&item.sig.output) else {
+                #item
+            }
+        }
+        Item::Impl(mut item) => {
+            let mut error_enums = Vec::new();
+            for impl_item in item.items.iter_mut() {
+                let ImplItem::Fn(method) = impl_item else {
+                    continue;
+                };
+                let Some(position) = method.attrs.iter().position(is_errgo_attr) else {
+                    continue;
+                };
+                let attr = method.attrs.remove(position);
+                let mut config = config.clone();
+                if let Meta::List(MetaList { tokens, .. }) = attr.meta {
+                    if let Err(e) = config.parse_more(tokens) {
+                        emit_error!(e.span(), "{}", e);
+                        continue;
+                    }
+                }
+                let generics = generics::merge(&item.generics, &method.sig.generics);
+                error_enums.push(expand(
+                    config,
+                    &method.vis,
+                    &mut method.sig,
+                    &generics,
+                    &mut method.block,
+                ));
+            }
+            quote! {
+                #(#error_enums)*
+
+                #item
+            }
+        }
+        Item::Mod(mut item) => {
+            let Some((_, items)) = &mut item.content else {
+                emit_error!(
+                    item,
+                    "unsupported module - `#[errgo]` must be placed on a module with its contents inline"
+                );
+                return quote!(#item).into();
+            };
+            let Some(error_name) = config.error.clone() else {
+                emit_error!(
+                    item.ident,
+                    "`#[errgo]` on a module must name the error with `#[errgo(error = SomeConcreteErr)]`"
+                );
+                return quote!(#item).into();
+            };
+            if let Some(define_in) = &config.define_in {
+                emit_error!(
+                    define_in,
+                    "`define_in` can't be used on a module - the error is defined in the module itself"
+                );
+            }
+            let error_vis = config
+                .visibility
+                .clone()
+                .unwrap_or_else(|| parse_quote!(pub));
+            let aliases = config.aliases.as_deref().unwrap_or_default();
+            // Functions with their own `#[errgo]` are left alone
+            let bodies = items.iter_mut().flat_map(|item| match item {
+                Item::Fn(item) if !item.attrs.iter().any(is_errgo_attr) => {
+                    vec![Body::new(&item.sig, &mut item.block, &error_name, aliases)]
+                }
+                Item::Impl(item) if !item.attrs.iter().any(is_errgo_attr) => item
+                    .items
+                    .iter_mut()
+                    .filter_map(|it| match it {
+                        ImplItem::Fn(method) if !method.attrs.iter().any(is_errgo_attr) => {
+                            Some(Body::new(&method.sig, &mut method.block, &error_name, aliases))
+                        }
+                        _ => None,
+                    })
+                    .collect(),
+                _ => vec![],
+            });
+            let bodies = bodies.collect::<Vec<_>>();
+            let error_enum = generate(
+                config,
+                &item.ident,
+                error_name,
+                error_vis,
+                Generics::default(),
+                bodies,
+            );
+            items.insert(0, Item::Verbatim(error_enum));
+            quote!(#item)
+        }
+        other => {
+            emit_error!(
+                other,
+                "unsupported item - `#[errgo]` must be placed on a function, an `impl` block or a module"
+            );
+            quote!(#other)
+        }
+    }
+    .into()
+}
+
+/// Rewrite the `err!` invocations in `block`, returning the definition of the generated enum.
+/// `generics` are those in scope for the function, which the enum may borrow.
+fn expand(
+    config: Config,
+    vis: &Visibility,
+    sig: &mut Signature,
+    generics: &Generics,
+    block: &mut Block,
+) -> TokenStream {
+    let aliases = config.aliases.as_deref().unwrap_or_default();
+    let returned = return_type::resolve(&sig.output, aliases);
+    let Some(PathSegment {
+        ident: error_name,
+        arguments,
+    }) = config
+        .error
+        .clone()
+        .map(PathSegment::from)
+        .or_else(|| returned.clone().map(|it| it.error))
+    else {
         emit_error!(
-            item.sig,
-            "unsupported return type - function must return a `Result<_, SomeConcreteErr>`"
+            sig,
+            "unsupported return type - function must return a `Result<_, SomeConcreteErr>` or a future of one, or name the error with `#[errgo(error = SomeConcreteErr)]`"
         );
-        return quote!(#item).into();
+        return quote!();
+    };
+    let error_vis = match (config.visibility.clone(), &config.define_in, vis) {
+        (Some(visibility), _, _) => visibility,
+        // The function must be able to name the error from outside its module
+        (None, Some(_), Visibility::Inherited) => parse_quote!(pub(crate)),
+        (None, _, _) => vis.clone(),
     };
-    let error_vis = config.visibility.unwrap_or_else(|| item.vis.clone());
+    let error_generics = match generics::carry(&arguments, generics) {
+        Ok(it) => it,
+        Err(e) => {
+            emit_error!(e.span(), "{}", e);
+            Generics::default()
+        }
+    };
+    let body = Body::new(sig, block, &error_name, aliases);
+    if let Some(module) = &config.define_in {
+        return_type::qualify(&mut sig.output, &error_name, module)
+    }
+    generate(
+        config,
+        &sig.ident,
+        error_name,
+        error_vis,
+        error_generics,
+        [body],
+    )
+}
+
+/// The body of a function to rewrite, with what its signature tells us.
+struct Body<'a> {
+    block: &'a mut Block,
+    /// Whether the function returns the error boxed, like `Result<_, Box<SomeConcreteErr>>`.
+    boxed: bool,
+    /// The variants the function returns its `Result` in, like `Option::Some` for `Option<Result<..>>`.
+    wrappers: Vec<Path>,
+    /// Parameters which are simple bindings, whose types fields may take.
+    params: Vec<(Ident, Type)>,
+}
+
+impl<'a> Body<'a> {
+    fn new(sig: &Signature, block: &'a mut Block, error_name: &Ident, aliases: &[Path]) -> Self {
+        let returned =
+            return_type::resolve(&sig.output, aliases).filter(|it| it.error.ident == *error_name);
+        let boxed = returned.as_ref().is_some_and(|it| it.boxed);
+        let wrappers = returned.map(|it| it.wrappers).unwrap_or_default();
+        let params = sig
+            .inputs
+            .iter()
+            .filter_map(|arg| match arg {
+                FnArg::Typed(PatType { pat, ty, .. }) => match &**pat {
+                    Pat::Ident(PatIdent {
+                        ident,
+                        by_ref: None,
+                        subpat: None,
+                        ..
+                    }) => Some((ident.clone(), (**ty).clone())),
+                    _ => None,
+                },
+                FnArg::Receiver(_) => None,
+            })
+            .collect();
+        Self {
+            block,
+            boxed,
+            wrappers,
+            params,
+        }
+    }
+}
 
+/// Rewrite the `err!` invocations in each of `bodies`, returning the definition of one enum with all their variants.
+///
+/// `item` is the function or module being expanded.
+fn generate<'a>(
+    config: Config,
+    item: &Ident,
+    error_name: Ident,
+    error_vis: Visibility,
+    error_generics: Generics,
+    bodies: impl IntoIterator<Item = Body<'a>>,
+) -> TokenStream {
     // Make the changes to the syntax tree, and collect the error variants
-    let mut visitor = ErrAsYouGoVisitor::new(error_name.clone());
-    visitor.visit_item_fn_mut(&mut item);
+    let bodies = bodies.into_iter().collect::<Vec<_>>();
+    let hidden = Hidden::all(&config, &error_name);
+    let mut visitor = ErrAsYouGoVisitor::new(error_name.clone(), hidden.clone());
+    visitor.define_in = config.define_in.clone();
+    visitor.variant_structs = config.variant_structs;
+    visitor.declared = Declared::find(&bodies);
+    for body in bodies {
+        visitor.boxed = body.boxed;
+        visitor.wrappers = body.wrappers;
+        visitor.params = body.params;
+        visitor.visit_block_mut(body.block);
+    }
+    visitor.resolve_references();
+    let conversions = visitor.resolve_conversions();
 
     for (src, e) in visitor.collection_errors {
         emit_error!(src, "{}", e)
     }
 
-    // Assemble our output
-    let variants = visitor.variants;
-    let derives = match config.derives {
+    let variants = visitor.variants.into_inner();
+    match (config.extend, &config.define_in) {
+        (true, Some(define_in)) => {
+            emit_error!(
+                define_in,
+                "`define_in` can't be used with `extend` - the error is defined where the hand-written enum is"
+            );
+            quote!()
+        }
+        (true, None) => extend::bridge(&config, &error_name, item, &variants, &conversions),
+        (false, define_in) => {
+            let definition = assemble(
+                &config,
+                &error_name,
+                &error_vis,
+                &error_generics,
+                &[],
+                variants,
+                &conversions,
+            );
+            let Some(define_in) = define_in else {
+                return definition;
+            };
+            let parent = match &config.parent {
+                Some(parent) if error_generics.params.is_empty() => {
+                    parent::implement_from(&define::qualify(define_in, &error_name), parent)
+                }
+                _ => quote!(),
+            };
+            let definition = define::wrap(&error_name, definition);
+            quote!(#definition #parent)
+        }
+    }
+}
+
+/// Define the enum with `variants`, and implement everything `config` asks for.
+///
+/// `attrs` are added to the enum after those from `config`.
+fn assemble(
+    config: &Config,
+    error_name: &Ident,
+    error_vis: &Visibility,
+    error_generics: &Generics,
+    attrs: &[Attribute],
+    mut variants: Vec<syn::Variant>,
+    conversions: &[(Ident, Type)],
+) -> TokenStream {
+    let hidden = Hidden::all(config, error_name);
+    let struct_module = structs::module(error_name);
+    let layout = match config.variant_structs {
+        true => Layout::Wrapped(&struct_module),
+        false => Layout::Inline,
+    };
+    let code = match config.code {
+        true => code::implement(
+            error_name,
+            error_vis,
+            error_generics,
+            &hidden,
+            &mut variants,
+            config,
+            layout,
+        ),
+        false => quote!(),
+    };
+    for param in generics::unused(error_generics, &variants) {
+        emit_error!(
+            param,
+            "generic parameter `{}` isn't used by any variant of `{}`",
+            param.to_token_stream(),
+            error_name
+        )
+    }
+    let where_clause = &error_generics.where_clause;
+    let derives = match &config.derives {
         Some(derives) => quote!(#[derive(
             #(#derives),*
         )]),
         None => quote!(),
     };
+    // Attributes go after the `derive`, so that helper attributes are in scope
+    let attributes = config.attributes.as_deref().unwrap_or_default();
+    // The messages are only read by `display` or thiserror
+    if config.default_display && (config.display || config.derives_error()) {
+        display::add_defaults(&mut variants, &hidden, config.display)
+    }
+    let mut struct_display = quote!();
+    let display = match config.display {
+        true => {
+            // Wrapped errors are the cause of their variant
+            for (ident, _) in conversions {
+                let variant = variants.iter_mut().find(|it| it.ident == *ident).unwrap();
+                if source::find(&variant.fields, false).is_none() {
+                    let field = variant.fields.iter_mut().next().unwrap();
+                    field.attrs.push(parse_quote!(#[source]))
+                }
+            }
+            // Each struct gets its own message, before the enum's implementation takes them
+            if config.variant_structs {
+                struct_display = structs::display(error_generics, &variants, config)
+            }
+            display::implement(error_name, error_generics, &mut variants, config, layout)
+        }
+        false => {
+            if config.provide {
+                emit_error!(
+                    error_name,
+                    "`backtrace(provide)` requires `display` - otherwise, provide the backtrace in your own `Error` implementation"
+                )
+            }
+            if config.source_by_type {
+                source::mark(&mut variants)
+            }
+            quote!()
+        }
+    };
+
+    let conversions =
+        conversions::implement(error_name, error_generics, &hidden, conversions, layout);
+    let hidden_fields = hidden::implement(
+        error_name,
+        error_vis,
+        error_generics,
+        &hidden,
+        &variants,
+        layout,
+    );
+    let kind = match config.kind {
+        true => kind::implement(error_name, error_vis, error_generics, &variants, layout),
+        false => quote!(),
+    };
+    let accessors = match config.accessors {
+        true => accessors::implement(
+            error_name,
+            error_vis,
+            error_generics,
+            &hidden,
+            &variants,
+            layout,
+        ),
+        false => quote!(),
+    };
+    let parent = match &config.parent {
+        Some(parent) if !error_generics.params.is_empty() => {
+            emit_error!(
+                parent,
+                "`parent` can't be used with a generic error - `{}` has generic parameters",
+                error_name
+            );
+            quote!()
+        }
+        // It's implemented where the function is instead, so `parent` is named from there
+        Some(_) if config.define_in.is_some() => quote!(),
+        Some(parent) => parent::implement_from(&Path::from(error_name.clone()), parent),
+        None => quote!(),
+    };
+
+    let impls = quote! {
+        #display
+        #conversions
+        #parent
+        #hidden_fields
+        #accessors
+        #kind
+        #code
+    };
+    let structs = match config.variant_structs {
+        true => {
+            let structs = structs::split(
+                error_name,
+                &struct_module,
+                &inner_visibility(error_vis),
+                error_generics,
+                &derives,
+                &hidden,
+                &mut variants,
+            );
+            let docs = format!("The structs wrapped by each variant of [`{error_name}`].");
+            quote! {
+                #[doc = #docs]
+                #error_vis mod #struct_module {
+                    #[allow(unused_imports)]
+                    use super::*;
+
+                    #struct_display
+                    #structs
+                }
+            }
+        }
+        false => quote!(),
+    };
 
     quote! {
         #derives
-        #error_vis enum #error_name {
+        #(#attributes)*
+        #(#attrs)*
+        #error_vis enum #error_name #error_generics #where_clause {
             #(#variants),*
         }
 
-        #item
+        #structs
+        #impls
     }
-    .into()
 }
 
-fn get_struct_name_from_return_type(return_type: &ReturnType) -> Option<Ident> {
-    if let ReturnType::Type(_, ty) = return_type {
-        if let syn::Type::Path(TypePath {
-            qself: None,
-            path: Path { ref segments, .. },
-        }) = **ty
-        {
-            if let Some(PathSegment {
-                ident,
-                arguments:
-                    PathArguments::AngleBracketed(AngleBracketedGenericArguments { args, .. }),
-            }) = segments.last()
-            {
-                if ident == "Result" && args.len() == 2 {
-                    if let Some(GenericArgument::Type(syn::Type::Path(TypePath {
-                        qself: None,
-                        path:
-                            Path {
-                                segments,
-                                leading_colon: None,
-                            },
-                    }))) = args.into_iter().nth(1)
-                    {
-                        if segments.len() == 1 {
-                            let PathSegment { ident, arguments } = &segments[0];
-                            if arguments.is_empty() {
-                                return Some(ident.clone());
-                            }
-                        }
-                    }
-                }
-            }
-        }
+fn is_errgo_attr(attr: &Attribute) -> bool {
+    let segments = &attr.path().segments;
+    match segments.len() {
+        1 => segments[0].ident == "errgo",
+        2 => segments[0].ident == "errgo" && segments[1].ident == "errgo",
+        _ => false,
     }
-    None
+}
+
+/// Declare a parent error, which wraps the errors of functions marked with `#[errgo(parent = ..)]`.
+///
+/// Each unit variant names a child error, and is replaced with a variant wrapping it -
+/// named for the child, without any `Error` suffix.
+/// Other variants are left as they are, as are unit variants marked `#[own]`.
+/// ```
+/// # use errgo::errgo;
+/// errgo::parent! {
+///     #[derive(Debug)]
+///     pub enum CrateError {
+///         FooError,
+///         BarError,
+///         Other(String),
+///         #[own]
+///         Cancelled,
+///     }
+/// }
+///
+/// #[errgo(parent = CrateError, derive(Debug))]
+/// fn foo() -> Result<(), FooError> { Err(err!(Bad)) }
+///
+/// #[errgo(parent = CrateError, derive(Debug))]
+/// fn bar() -> Result<(), BarError> { Err(err!(Worse)) }
+///
+/// fn api() -> Result<(), CrateError> {
+///     foo()?;
+///     bar()?;
+///     Ok(())
+/// }
+/// ```
+/// Where `CrateError` is generated like so:
+/// ```
+/// # #[derive(Debug)] pub struct FooError; #[derive(Debug)] pub struct BarError;
+/// #[derive(Debug)]
+/// pub enum CrateError {
+///     Foo(FooError),
+///     Bar(BarError),
+///     Other(String),
+///     Cancelled,
+/// }
+/// ```
+#[proc_macro]
+pub fn parent(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let item = parse_macro_input!(input as ItemEnum);
+    parent::expand(item).into_token_stream().into()
+}
+
+/// Mark a hand-written enum as one which `#[errgo(extend)]` may add variants to.
+///
+/// This is useful for an error with stable, documented variants, where only the ad-hoc extras should be generated.
+/// ```
+/// # use errgo::errgo;
+/// #[errgo::extendable]
+/// #[derive(Debug)]
+/// pub enum DbError {
+///     Timeout,
+///     Closed { reason: String },
+/// }
+///
+/// #[errgo(extend)]
+/// fn query(sql: &str) -> Result<(), DbError> {
+///     if sql.is_empty() {
+///         return Err(DbError::Timeout);
+///     }
+///     Err(err!(Syntax { sql: String = sql.into() }))
+/// }
+/// ```
+/// The enum is defined as written until a function extends it, when it's defined by that function's expansion instead -
+/// so the function must be in the same module as the enum.
+/// Only one function may extend it, and another is reported -
+/// use `#[errgo(error = DbError, extend)]` on a module to extend it from several functions.
+/// The enum as written is defined in a child module, so it can't name items through `self::` or `super::`.
+///
+/// `err!` variants can't share a name with a hand-written one.
+/// The enum's own visibility, generics and attributes are kept, and any `derive`s or `attributes` are added to them.
+/// `location` and `backtrace` can't be used, as the hand-written variants don't have their fields.
+#[proc_macro_attribute]
+#[proc_macro_error]
+pub fn extendable(
+    attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    if !attr.is_empty() {
+        emit_error!(
+            TokenStream::from(attr),
+            "`#[errgo::extendable]` doesn't take any arguments"
+        );
+    }
+    let item = parse_macro_input!(item as ItemEnum);
+    extend::extendable(&item).into()
+}
+
+/// Define the errors of functions marked with `#[errgo(define_in = ..)]` here, in the module they name.
+///
+/// Each error is named by the module of the function which declares it.
+/// ```
+/// # use errgo::errgo;
+/// mod errors {
+///     errgo::collect!(super::FooError, super::api::BarError);
+/// }
+///
+/// #[errgo(define_in = errors, derive(Debug))]
+/// fn foo() -> Result<(), FooError> { Err(err!(Bad)) }
+///
+/// mod api {
+///     # use errgo::errgo;
+///     #[errgo(define_in = crate::errors, derive(Debug))]
+///     pub fn bar() -> Result<(), BarError> { Err(err!(Worse)) }
+/// }
+///
+/// fn main() {
+///     assert!(matches!(foo(), Err(errors::FooError::Bad)));
+///     assert!(matches!(api::bar(), Err(errors::BarError::Worse)));
+/// }
+/// ```
+/// Each error must be collected exactly once.
+/// Types in its variants are resolved in the collecting module, so they may need to be written as full paths.
+/// `define_in` can't be used on a module, or with `extend`.
+/// A `parent` is still named from the function's module, as its `From` implementation is placed there.
+#[proc_macro]
+pub fn collect(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let errors = parse_macro_input!(input with Punctuated::<Path, Token![,]>::parse_terminated);
+    define::collect(errors).into()
+}
+
+/// Implementation detail
+// Defines an `#[errgo::extendable]` enum with the variants from `#[errgo(extend)]`.
+#[doc(hidden)]
+#[proc_macro]
+#[proc_macro_error]
+pub fn __extend(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let extension = parse_macro_input!(input as extend::Extension);
+    extend::expand(extension).into()
 }
 
 /// Implementation detail
@@ -249,36 +1152,400 @@ pub fn __nothing(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
 
 struct ErrAsYouGoVisitor {
     error_name: Ident,
-    variants: Vec<syn::Variant>,
+    /// The module the error is defined in, if it's not alongside the function.
+    define_in: Option<Path>,
+    /// Whether each variant wraps a struct of the same name.
+    variant_structs: bool,
+    /// Fields added to the end of every variant.
+    hidden: Vec<Hidden>,
+    variants: Variants,
+    /// Variants which are missing types, to be found once all the declarations are collected.
+    references: Vec<VariantWithValue>,
+    /// Variants used by `try_err!`, which should be converted from the error they wrap.
+    conversions: Vec<Ident>,
+    /// Whether the function being visited returns the error boxed, so `err!` should box it.
+    boxed: bool,
+    /// The variants the function being visited returns its `Result` in, for `check!` to return through.
+    wrappers: Vec<Path>,
+    /// The parameters of the function being visited, for [`VariantWithValue::infer_types`].
+    params: Vec<(Ident, Type)>,
+    /// Variants whose types are given by some `err!`, so others may leave them out.
+    declared: HashSet<Ident>,
     collection_errors: Vec<(TokenStream, syn::Error)>,
 }
 
 impl ErrAsYouGoVisitor {
-    fn new(error_name: Ident) -> Self {
+    fn new(error_name: Ident, hidden: Vec<Hidden>) -> Self {
         Self {
             error_name,
-            variants: Vec::new(),
+            define_in: None,
+            variant_structs: false,
+            hidden,
+            variants: Variants::default(),
+            references: Vec::new(),
+            conversions: Vec::new(),
+            boxed: false,
+            wrappers: Vec::new(),
+            params: Vec::new(),
+            declared: HashSet::new(),
             collection_errors: Vec::new(),
         }
     }
+
+    /// The path to the error from the function.
+    fn error_path(&self) -> Path {
+        match &self.define_in {
+            Some(module) => define::qualify(module, &self.error_name),
+            None => Path::from(self.error_name.clone()),
+        }
+    }
+
+    /// Construct the `variant` of the error, given how to build it with a prefix.
+    /// With `variant_structs`, it's built from its struct and wrapped in the variant.
+    fn construct(&self, variant: &Ident, build: impl FnOnce(Path) -> Expr) -> Expr {
+        let error_path = self.error_path();
+        match self.variant_structs {
+            true => {
+                let built = build(self.struct_module());
+                parse_quote!(#error_path::#variant(#built))
+            }
+            false => build(error_path),
+        }
+    }
+
+    /// The path to the module of the structs from `variant_structs`.
+    fn struct_module(&self) -> Path {
+        let module = structs::module(&self.error_name);
+        match &self.define_in {
+            Some(define_in) => define::qualify(define_in, &module),
+            None => Path::from(module),
+        }
+    }
+
+    /// The value of a hidden field, for an error created at `span`.
+    fn hidden_value(&self, hidden: &Hidden, span: Span) -> Expr {
+        match &self.define_in {
+            Some(module) => hidden.value_in(module, span),
+            None => hidden.value(span),
+        }
+    }
+
+    fn insert(&mut self, variant: syn::Variant) {
+        let ident = variant.ident.clone();
+        if let Err(conflict) = self.variants.insert(variant) {
+            self.collection_errors.push((
+                conflict.here,
+                syn::Error::new(Span::call_site(), conflict.message),
+            ));
+            self.collection_errors.push((
+                conflict.first,
+                syn::Error::new(Span::call_site(), format!("`{}` first defined here", ident)),
+            ));
+        }
+    }
+
+    /// The error for a variant which is used without types, but never declared with them.
+    fn undeclared(&self, ident: &Ident) -> (TokenStream, syn::Error) {
+        let known = self
+            .variants
+            .idents()
+            .map(|it| format!("`{}`", it))
+            .collect::<Vec<_>>();
+        let known = match known.is_empty() {
+            true => String::from("no variants have been declared"),
+            false => format!("known variants are {}", known.join(", ")),
+        };
+        (
+            ident.to_token_stream(),
+            syn::Error::new(
+                Span::call_site(),
+                format!(
+                    "`{}` must be declared with types somewhere in this function - {}",
+                    ident, known
+                ),
+            ),
+        )
+    }
+
+    /// Fill in the types of variants which were used without them.
+    fn resolve_references(&mut self) {
+        for mut reference in std::mem::take(&mut self.references) {
+            let Some(declaration) = self.variants.get(&reference.ident) else {
+                let error = self.undeclared(&reference.ident);
+                self.collection_errors.push(error);
+                continue;
+            };
+            if let Err(e) = reference.fill_types(declaration) {
+                self.collection_errors.push((e.to_compile_error(), e));
+                continue;
+            }
+            self.insert(reference.into_syn_variant())
+        }
+    }
+
+    /// The variants used by `try_err!`, and the error types they wrap.
+    ///
+    /// Each type may only be wrapped by one variant, or the `From` implementations would overlap.
+    fn resolve_conversions(&mut self) -> Vec<(Ident, Type)> {
+        let mut conversions = Vec::<(Ident, Type)>::new();
+        for ident in std::mem::take(&mut self.conversions) {
+            if conversions.iter().any(|(it, _)| *it == ident) {
+                continue;
+            }
+            let Some(declaration) = self.variants.get(&ident) else {
+                let error = self.undeclared(&ident);
+                self.collection_errors.push(error);
+                continue;
+            };
+            let wrapped = match &declaration.fields {
+                Fields::Unnamed(fields) => fields
+                    .unnamed
+                    .iter()
+                    .filter(|it| !hidden::is_hidden(it, &self.hidden))
+                    .collect(),
+                _ => vec![],
+            };
+            let ty = match wrapped[..] {
+                [field] => field.ty.clone(),
+                _ => {
+                    self.collection_errors.push((
+                        ident.to_token_stream(),
+                        syn::Error::new(
+                            Span::call_site(),
+                            format!("`{}` must have exactly one unnamed field to be used with `try_err!`", ident),
+                        ),
+                    ));
+                    continue;
+                }
+            };
+            if let Some((first, _)) = conversions.iter().find(|(_, it)| *it == ty) {
+                self.collection_errors.push((
+                    ident.to_token_stream(),
+                    syn::Error::new(
+                        Span::call_site(),
+                        format!(
+                            "`{}` and `{}` both wrap `{}` - only one variant can be converted from each type",
+                            first,
+                            ident,
+                            type_name(&ty)
+                        ),
+                    ),
+                ));
+                self.collection_errors.push((
+                    first.to_token_stream(),
+                    syn::Error::new(
+                        Span::call_site(),
+                        format!("`{}` first wraps `{}` here", first, type_name(&ty)),
+                    ),
+                ));
+                continue;
+            }
+            conversions.push((ident, ty))
+        }
+        conversions
+    }
 }
 
 impl syn::visit_mut::VisitMut for ErrAsYouGoVisitor {
     fn visit_macro_mut(&mut self, i: &mut syn::Macro) {
         if i.path.is_ident("err") || i.path.is_ident("errgo") {
             match parse2::<VariantWithValue>(i.tokens.clone()) {
-                Ok(variant_with_value) => {
-                    self.variants
-                        .push(variant_with_value.clone().into_syn_variant());
+                Ok(mut variant_with_value) => {
+                    let declared = self.declared.contains(&variant_with_value.ident);
+                    if let Err(e) = variant_with_value
+                        .infer_types(&self.params, declared)
+                        .and_then(|()| variant_with_value.capture_message_args(&self.params))
+                    {
+                        self.collection_errors.push((i.tokens.clone(), e));
+                        return;
+                    }
+                    if !declared {
+                        variant_with_value.use_shorthand()
+                    }
+                    for hidden in &self.hidden {
+                        variant_with_value.push_field(
+                            hidden.ident.clone(),
+                            hidden.ty.clone(),
+                            self.hidden_value(hidden, i.path.span()),
+                        )
+                    }
+                    match variant_with_value.is_typed() {
+                        true => self.insert(variant_with_value.clone().into_syn_variant()),
+                        false => self.references.push(variant_with_value.clone()),
+                    }
+                    let ident = variant_with_value.ident.clone();
+                    let construction = self.construct(&ident, |prefix| {
+                        variant_with_value.into_syn_expr_with_prefix(prefix)
+                    });
                     i.path = path(["errgo", "__nothing"]);
-                    i.tokens = variant_with_value
-                        .into_syn_expr_with_prefix(Path::from(self.error_name.clone()))
+                    // `try_err!` and `check!` are boxed by `?` instead
+                    i.tokens = match self.boxed {
+                        true => quote!(::std::boxed::Box::new(#construction)),
+                        false => construction.into_token_stream(),
+                    };
+                }
+                Err(e) => self.collection_errors.push((i.tokens.clone(), e)),
+            }
+        } else if i.path.is_ident("check") {
+            match parse2::<Check>(i.tokens.clone()) {
+                Ok(mut check) => {
+                    self.visit_expr_mut(&mut check.expr);
+                    let mut variant = check.variant();
+                    for hidden in &self.hidden {
+                        variant.push_field(
+                            hidden.ident.clone(),
+                            hidden.ty.clone(),
+                            self.hidden_value(hidden, i.path.span()),
+                        )
+                    }
+                    self.insert(variant.clone().into_syn_variant());
+                    let ident = variant.ident.clone();
+                    let construction =
+                        self.construct(&ident, |prefix| variant.into_syn_expr_with_prefix(prefix));
+                    i.path = path(["errgo", "__nothing"]);
+                    i.tokens = check
+                        .into_syn_expr(construction, &self.wrappers)
                         .into_token_stream();
                 }
                 Err(e) => self.collection_errors.push((i.tokens.clone(), e)),
             }
+        } else if i.path.is_ident("try_err") {
+            match parse2::<TryErr>(i.tokens.clone()) {
+                Ok(mut try_err) => {
+                    self.visit_expr_mut(&mut try_err.expr);
+                    try_err.hidden = self
+                        .hidden
+                        .iter()
+                        .map(|it| (it.ty.clone(), self.hidden_value(it, i.path.span())))
+                        .collect();
+                    if let Some(variant) = try_err.to_syn_variant() {
+                        self.insert(variant)
+                    }
+                    self.conversions.push(try_err.ident.clone());
+                    i.path = path(["errgo", "__nothing"]);
+                    let error_path = self.error_path();
+                    let ident = try_err.ident.clone();
+                    let construction = match self.variant_structs {
+                        // The struct is wrapped in the variant by a second `map_err`
+                        true => {
+                            let construction =
+                                try_err.into_syn_expr_with_prefix(self.struct_module());
+                            parse_quote!(::core::result::Result::map_err(#construction, #error_path::#ident))
+                        }
+                        false => try_err.into_syn_expr_with_prefix(error_path),
+                    };
+                    i.tokens = construction.into_token_stream();
+                }
+                Err(e) => self.collection_errors.push((i.tokens.clone(), e)),
+            }
+        }
+    }
+}
+
+/// Find the variants whose types are given by an `err!` in any of the bodies, before they're rewritten.
+struct Declared<'a> {
+    params: &'a [(Ident, Type)],
+    declared: HashSet<Ident>,
+}
+
+impl Declared<'_> {
+    fn find(bodies: &[Body]) -> HashSet<Ident> {
+        let mut declared = HashSet::new();
+        for body in bodies {
+            let mut visitor = Declared {
+                params: &body.params,
+                declared,
+            };
+            visitor.visit_block(body.block);
+            declared = visitor.declared;
+        }
+        declared
+    }
+}
+
+impl<'ast> Visit<'ast> for Declared<'_> {
+    fn visit_macro(&mut self, i: &'ast syn::Macro) {
+        if i.path.is_ident("err") || i.path.is_ident("errgo") {
+            if let Ok(mut variant) = parse2::<VariantWithValue>(i.tokens.clone()) {
+                if variant.infer_types(self.params, false).is_ok() && variant.is_typed() {
+                    self.declared.insert(variant.ident);
+                }
+            }
+        } else if i.path.is_ident("check") {
+            if let Ok(check) = parse2::<Check>(i.tokens.clone()) {
+                self.visit_expr(&check.expr)
+            }
+        } else if i.path.is_ident("try_err") {
+            if let Ok(try_err) = parse2::<TryErr>(i.tokens.clone()) {
+                self.visit_expr(&try_err.expr)
+            }
+        }
+    }
+}
+
+/// A pattern for `variant` on `Self`, binding its fields by name, or as `_0`, `_1`... for tuple variants.
+fn pattern(variant: &syn::Variant, layout: Layout<'_>) -> TokenStream {
+    let ident = &variant.ident;
+    let fields = match &variant.fields {
+        Fields::Named(fields) => {
+            let names = fields.named.iter().map(|it| &it.ident);
+            quote!({ #(#names),* })
+        }
+        Fields::Unnamed(fields) => {
+            let names = (0..fields.unnamed.len()).map(|index| format_ident!("_{}", index));
+            quote!((#(#names),*))
+        }
+        Fields::Unit => quote!(),
+    };
+    layout.path(ident, fields)
+}
+
+/// `ty` as it would be written, like `io::Error` rather than the spaced out tokens `io :: Error`.
+fn type_name(ty: &Type) -> String {
+    let tokens = ty.to_token_stream().to_string();
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let mut name = String::with_capacity(tokens.len());
+    let mut chars = tokens.chars().peekable();
+    // Whether `name` ends in a lifetime, which is always followed by a space
+    let mut lifetime = false;
+    while let Some(c) = chars.next() {
+        let keep = match (name.chars().last(), chars.peek()) {
+            (Some(before), Some(&after)) => {
+                lifetime
+                    || (is_word(before) && is_word(after))
+                    || matches!(before, ',' | ';' | '+')
+                    || after == '+'
+            }
+            _ => false,
+        };
+        if c != ' ' || keep {
+            name.push(c)
         }
+        lifetime = c == '\'' || (lifetime && is_word(c));
     }
+    name
+}
+
+/// `vis` for an item moved into a child module, so that it's visible from the same places.
+fn inner_visibility(vis: &Visibility) -> Visibility {
+    let Visibility::Restricted(restricted) = vis else {
+        return match vis {
+            Visibility::Inherited => parse_quote!(pub(super)),
+            _ => vis.clone(),
+        };
+    };
+    let mut path = (*restricted.path).clone();
+    match path
+        .segments
+        .first()
+        .map(|it| it.ident.to_string())
+        .as_deref()
+    {
+        Some("self") => path.segments[0] = PathSegment::from(ident("super")),
+        Some("super") => path.segments.insert(0, PathSegment::from(ident("super"))),
+        _ => return vis.clone(),
+    }
+    parse_quote!(pub(in #path))
 }
 
 fn path<'a>(segments: impl IntoIterator<Item = &'a str>) -> Path {
@@ -332,17 +1599,70 @@ mod tests {
     }
 
     #[test]
-    fn get_result_name() {
-        let ident = get_struct_name_from_return_type(
-            &syn::parse2(quote!(-> Result<T, SomeConcreteErr>)).unwrap(),
-        )
-        .unwrap();
-        assert_eq!(ident, "SomeConcreteErr");
+    fn inner_visibilities() {
+        for (vis, expected) in [
+            (quote!(), quote!(pub(super))),
+            (quote!(pub), quote!(pub)),
+            (quote!(pub(crate)), quote!(pub(crate))),
+            (quote!(pub(self)), quote!(pub(in super))),
+            (quote!(pub(super)), quote!(pub(in super::super))),
+            (quote!(pub(in crate::db)), quote!(pub(in crate::db))),
+            (quote!(pub(in super::db)), quote!(pub(in super::super::db))),
+        ] {
+            assert_eq!(
+                parse2::<Visibility>(expected).unwrap(),
+                inner_visibility(&parse2(vis).unwrap())
+            );
+        }
+    }
 
-        let ident = get_struct_name_from_return_type(
-            &syn::parse2(quote!(-> ::std::result::Result<T, SomeConcreteErr>)).unwrap(),
-        )
+    #[test]
+    fn type_names() {
+        for (ty, expected) in [
+            (quote!(io::Error), "io::Error"),
+            (quote!(&'a [u8; 4]), "&'a [u8; 4]"),
+            (
+                quote!(Box<dyn std::error::Error + Send>),
+                "Box<dyn std::error::Error + Send>",
+            ),
+            (quote!(HashMap<K, V>), "HashMap<K, V>"),
+        ] {
+            assert_eq!(expected, type_name(&parse2(ty).unwrap()));
+        }
+    }
+
+    #[test]
+    fn expand_attributes() {
+        let config = parse2::<Config>(quote! {
+            derive(Debug),
+            attributes(
+                #[repr(u8)],
+                #[non_exhaustive],
+                #[must_use = "handle me"],
+                #[doc = "some docs"]
+            )
+        })
+        .unwrap();
+        let mut item = parse2::<syn::ItemFn>(quote! {
+            fn foo() -> Result<(), FooError> {
+                Err(err!(Bar))
+            }
+        })
+        .unwrap();
+        let generics = item.sig.generics.clone();
+        let expanded = expand(config, &item.vis, &mut item.sig, &generics, &mut item.block);
+        let expanded = parse2::<syn::ItemEnum>(expanded).unwrap();
+        let expected = parse2::<syn::ItemEnum>(quote! {
+            #[derive(Debug)]
+            #[repr(u8)]
+            #[non_exhaustive]
+            #[must_use = "handle me"]
+            #[doc = "some docs"]
+            enum FooError {
+                Bar
+            }
+        })
         .unwrap();
-        assert_eq!(ident, "SomeConcreteErr");
+        assert_eq!(expected, expanded);
     }
 }
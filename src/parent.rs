@@ -0,0 +1,95 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{parse_quote, Attribute, Fields, Ident, ItemEnum, Path};
+
+/// The name of the variant wrapping `child` in its parent, e.g `Foo` for `FooError`.
+pub fn variant(child: &Ident) -> Ident {
+    let name = child.to_string();
+    match name.strip_suffix("Error") {
+        Some(stripped) if !stripped.is_empty() => {
+            format_ident!("{}", stripped, span = child.span())
+        }
+        _ => child.clone(),
+    }
+}
+
+/// Implement `From<child>` for the `parent` enum.
+pub fn implement_from(child: &Path, parent: &Path) -> TokenStream {
+    let name = &child.segments.last().expect("a path has a segment").ident;
+    let variant = variant(name);
+    quote! {
+        impl ::core::convert::From<#child> for #parent {
+            fn from(source: #child) -> Self {
+                Self::#variant(source)
+            }
+        }
+    }
+}
+
+/// Whether `attr` is `#[own]`, marking a unit variant as the parent's own rather than a child.
+fn is_own_attr(attr: &Attribute) -> bool {
+    attr.path().is_ident("own")
+}
+
+/// Expand a parent enum, replacing each unit variant (like `FooError`) with one wrapping that child (like `Foo(FooError)`).
+///
+/// Unit variants marked `#[own]` are kept, without the marker.
+pub fn expand(mut item: ItemEnum) -> ItemEnum {
+    for child in item.variants.iter_mut() {
+        let own = child.attrs.iter().any(is_own_attr);
+        child.attrs.retain(|it| !is_own_attr(it));
+        if let (Fields::Unit, None, false) = (&child.fields, &child.discriminant, own) {
+            let ty = &child.ident;
+            child.fields = Fields::Unnamed(parse_quote!((#ty)));
+            child.ident = variant(ty);
+        }
+    }
+    item
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ident;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn variant_names() {
+        for (child, expected) in [
+            ("FooError", "Foo"),
+            ("Foo", "Foo"),
+            ("Error", "Error"),
+            ("ErrorError", "Error"),
+        ] {
+            assert_eq!(ident(expected), variant(&ident(child)))
+        }
+    }
+
+    #[test]
+    fn expand_parent() {
+        let expanded = expand(parse_quote! {
+            #[derive(Debug)]
+            pub enum CrateError {
+                FooError,
+                #[error(transparent)]
+                BarError,
+                Other(String),
+                #[own]
+                #[doc = "Cancelled"]
+                Cancelled,
+            }
+        });
+        let expected: ItemEnum = parse_quote! {
+            #[derive(Debug)]
+            pub enum CrateError {
+                Foo(FooError),
+                #[error(transparent)]
+                Bar(BarError),
+                Other(String),
+                #[doc = "Cancelled"]
+                Cancelled,
+            }
+        };
+        assert_eq!(expected, expanded);
+    }
+}
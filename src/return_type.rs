@@ -0,0 +1,341 @@
+use quote::format_ident;
+use syn::{
+    parse_quote,
+    visit_mut::{self, VisitMut},
+    AngleBracketedGenericArguments, GenericArgument, Ident, Path, PathArguments, PathSegment,
+    ReturnType, Type, TypeImplTrait, TypeParamBound, TypePath, TypeTraitObject,
+};
+
+/// The error named by a function's return type, like `SomeConcreteErr` in `Result<_, SomeConcreteErr>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Returned {
+    pub error: PathSegment,
+    /// Whether the error is returned boxed, as in `Result<_, Box<SomeConcreteErr>>`.
+    pub boxed: bool,
+    /// The variants the `Result` is returned in, outermost first, like `Option::Some` for `Option<Result<..>>`.
+    pub wrappers: Vec<Path>,
+}
+
+/// Find the error in `return_type`, looking through the wrappers below to a `Result<_, SomeConcreteErr>`.
+/// - `Poll<..>` and `Option<..>`
+/// - `ControlFlow<..>`, in either its break or continue type
+/// - futures, like `impl Future<Output = ..>`, `Pin<Box<dyn Future<Output = ..>>>` or `BoxFuture<'_, ..>`
+///
+/// `aliases` are other paths which should be treated like `Result`, e.g `crate::Result`.
+/// Only their last segment is compared.
+pub fn resolve(return_type: &ReturnType, aliases: &[Path]) -> Option<Returned> {
+    match return_type {
+        ReturnType::Type(_, ty) => in_type(ty, aliases),
+        ReturnType::Default => None,
+    }
+}
+
+/// Rewrite each mention of the bare `error` in `return_type` to its path in `module`, keeping any generic arguments.
+pub fn qualify(return_type: &mut ReturnType, error: &Ident, module: &Path) {
+    struct Qualify<'a> {
+        error: &'a Ident,
+        module: &'a Path,
+    }
+    impl VisitMut for Qualify<'_> {
+        fn visit_type_path_mut(&mut self, i: &mut TypePath) {
+            let bare =
+                i.qself.is_none() && i.path.leading_colon.is_none() && i.path.segments.len() == 1;
+            if bare && i.path.segments[0].ident == *self.error {
+                let mut qualified = self.module.clone();
+                qualified.segments.push(i.path.segments[0].clone());
+                i.path = qualified;
+                return;
+            }
+            visit_mut::visit_type_path_mut(self, i)
+        }
+    }
+    Qualify { error, module }.visit_return_type_mut(return_type)
+}
+
+fn in_type(ty: &Type, aliases: &[Path]) -> Option<Returned> {
+    match ty {
+        Type::Path(TypePath { qself: None, path }) => {
+            let (ident, types) = generic(path)?;
+            let is_result = ident == "Result"
+                || aliases
+                    .iter()
+                    .filter_map(|alias| alias.segments.last())
+                    .any(|alias| alias.ident == *ident);
+            match (ident.to_string().as_str(), &types[..]) {
+                (_, [_, error]) if is_result => error_type(error),
+                ("Poll", [inner]) => wrapped(
+                    in_type(inner, aliases),
+                    parse_quote!(::core::task::Poll::Ready),
+                ),
+                ("Option", [inner]) => wrapped(
+                    in_type(inner, aliases),
+                    parse_quote!(::core::option::Option::Some),
+                ),
+                ("ControlFlow", types) => {
+                    types
+                        .iter()
+                        .zip(["Break", "Continue"])
+                        .find_map(|(it, variant)| {
+                            let variant = format_ident!("{}", variant);
+                            wrapped(
+                                in_type(it, aliases),
+                                parse_quote!(::core::ops::ControlFlow::#variant),
+                            )
+                        })
+                }
+                // `Pin<Box<dyn Future<Output = ..>>>`
+                ("Pin" | "Box", [inner]) => in_type(inner, aliases),
+                // `BoxFuture<'a, ..>` from `futures`
+                ("BoxFuture" | "LocalBoxFuture", [output]) => in_type(output, aliases),
+                _ => None,
+            }
+        }
+        // `impl Future<Output = ..>` or `dyn Future<Output = ..>`
+        Type::ImplTrait(TypeImplTrait { bounds, .. })
+        | Type::TraitObject(TypeTraitObject { bounds, .. }) => bounds.iter().find_map(|bound| {
+            let TypeParamBound::Trait(bound) = bound else {
+                return None;
+            };
+            let PathSegment {
+                ident,
+                arguments:
+                    PathArguments::AngleBracketed(AngleBracketedGenericArguments { args, .. }),
+            } = bound.path.segments.last()?
+            else {
+                return None;
+            };
+            if ident != "Future" {
+                return None;
+            }
+            args.iter().find_map(|arg| match arg {
+                GenericArgument::AssocType(it) if it.ident == "Output" => in_type(&it.ty, aliases),
+                _ => None,
+            })
+        }),
+        Type::Paren(it) => in_type(&it.elem, aliases),
+        Type::Group(it) => in_type(&it.elem, aliases),
+        _ => None,
+    }
+}
+
+/// `returned`, found inside the `wrapper` variant.
+fn wrapped(returned: Option<Returned>, wrapper: Path) -> Option<Returned> {
+    let mut returned = returned?;
+    returned.wrappers.insert(0, wrapper);
+    Some(returned)
+}
+
+/// The error type itself, which must be a single segment, or one in a `Box`.
+fn error_type(ty: &Type) -> Option<Returned> {
+    let Type::Path(TypePath { qself: None, path }) = ty else {
+        return None;
+    };
+    match generic(path) {
+        Some((ident, types)) if ident == "Box" => match types[..] {
+            [inner] => Some(Returned {
+                error: single_segment(inner)?,
+                boxed: true,
+                wrappers: Vec::new(),
+            }),
+            _ => None,
+        },
+        _ => Some(Returned {
+            error: single_segment(ty)?,
+            boxed: false,
+            wrappers: Vec::new(),
+        }),
+    }
+}
+
+fn single_segment(ty: &Type) -> Option<PathSegment> {
+    match ty {
+        Type::Path(TypePath {
+            qself: None,
+            path:
+                Path {
+                    segments,
+                    leading_colon: None,
+                },
+        }) if segments.len() == 1 => Some(segments[0].clone()),
+        _ => None,
+    }
+}
+
+/// The last segment of `path`, with the types among its generic arguments.
+fn generic(path: &Path) -> Option<(&syn::Ident, Vec<&Type>)> {
+    let PathSegment {
+        ident,
+        arguments: PathArguments::AngleBracketed(AngleBracketedGenericArguments { args, .. }),
+    } = path.segments.last()?
+    else {
+        return None;
+    };
+    let types = args
+        .iter()
+        .filter_map(|arg| match arg {
+            GenericArgument::Type(it) => Some(it),
+            _ => None,
+        })
+        .collect();
+    Some((ident, types))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path;
+    use pretty_assertions::assert_eq;
+    use quote::quote;
+
+    fn error(return_type: proc_macro2::TokenStream, aliases: &[Path]) -> Option<Returned> {
+        resolve(&syn::parse2(return_type).unwrap(), aliases)
+    }
+
+    #[test]
+    fn result() {
+        let segment = error(quote!(-> Result<T, SomeConcreteErr>), &[]).unwrap();
+        assert_eq!(segment.error.ident, "SomeConcreteErr");
+        assert!(!segment.boxed);
+
+        let segment = error(quote!(-> ::std::result::Result<T, SomeConcreteErr>), &[]).unwrap();
+        assert_eq!(segment.error.ident, "SomeConcreteErr");
+
+        let segment = error(
+            quote!(-> crate::Fallible<T, SomeConcreteErr>),
+            &[path(["crate", "Fallible"])],
+        )
+        .unwrap();
+        assert_eq!(segment.error.ident, "SomeConcreteErr");
+
+        assert!(error(quote!(-> crate::Fallible<T, SomeConcreteErr>), &[]).is_none());
+        let segment = error(quote!(-> Result<T, ParseError<'a, T>>), &[]).unwrap();
+        assert_eq!(
+            segment.error,
+            syn::parse2(quote!(ParseError<'a, T>)).unwrap()
+        );
+
+        assert!(error(quote!(-> io::Result<T>), &[]).is_none());
+        assert!(error(quote!(-> Result<T, io::Error>), &[]).is_none());
+        assert!(error(quote!(-> Result<T, Box<dyn Error>>), &[]).is_none());
+    }
+
+    #[test]
+    fn future() {
+        for return_type in [
+            quote!(-> impl Future<Output = Result<T, SomeConcreteErr>>),
+            quote!(-> impl Future<Output = Result<T, SomeConcreteErr>> + Send + 'a),
+            quote!(-> impl std::future::Future<Output = Result<T, SomeConcreteErr>>),
+            quote!(-> Pin<Box<dyn Future<Output = Result<T, SomeConcreteErr>> + Send>>),
+            quote!(-> std::pin::Pin<Box<dyn Future<Output = Result<T, SomeConcreteErr>>>>),
+            quote!(-> BoxFuture<'a, Result<T, SomeConcreteErr>>),
+            quote!(-> futures::future::LocalBoxFuture<'static, Result<T, SomeConcreteErr>>),
+        ] {
+            let segment = error(return_type.clone(), &[])
+                .unwrap_or_else(|| panic!("no error in {}", return_type));
+            assert_eq!(segment.error.ident, "SomeConcreteErr");
+        }
+        for return_type in [
+            quote!(-> impl Future<Output = ()>),
+            quote!(-> impl Iterator<Item = Result<T, SomeConcreteErr>>),
+            quote!(-> Pin<Box<T>>),
+        ] {
+            assert!(error(return_type.clone(), &[]).is_none(), "{}", return_type);
+        }
+    }
+
+    #[test]
+    fn qualified() {
+        for (return_type, expected) in [
+            (
+                quote!(-> Result<T, FooError>),
+                quote!(-> Result<T, crate::errors::FooError>),
+            ),
+            (
+                quote!(-> Result<T, Box<FooError<'a, T>>>),
+                quote!(-> Result<T, Box<crate::errors::FooError<'a, T>>>),
+            ),
+            (
+                quote!(-> impl Future<Output = Result<FooError, other::FooError>>),
+                quote!(-> impl Future<Output = Result<crate::errors::FooError, other::FooError>>),
+            ),
+        ] {
+            let mut return_type = syn::parse2(return_type).unwrap();
+            qualify(
+                &mut return_type,
+                &crate::ident("FooError"),
+                &path(["crate", "errors"]),
+            );
+            assert_eq!(syn::parse2::<ReturnType>(expected).unwrap(), return_type);
+        }
+    }
+
+    #[test]
+    fn wrapped() {
+        for (return_type, boxed) in [
+            (quote!(-> Poll<Result<T, SomeConcreteErr>>), false),
+            (
+                quote!(-> std::task::Poll<Result<T, SomeConcreteErr>>),
+                false,
+            ),
+            (quote!(-> Option<Result<T, SomeConcreteErr>>), false),
+            (quote!(-> ControlFlow<Result<T, SomeConcreteErr>>), false),
+            (
+                quote!(-> std::ops::ControlFlow<B, Result<T, Box<SomeConcreteErr>>>),
+                true,
+            ),
+            (quote!(-> Result<T, Box<SomeConcreteErr>>), true),
+            (quote!(-> Result<T, std::boxed::Box<SomeConcreteErr>>), true),
+            (
+                quote!(-> Poll<Option<Result<T, Box<SomeConcreteErr>>>>),
+                true,
+            ),
+            (
+                quote!(-> impl Future<Output = Result<T, Box<SomeConcreteErr>>>),
+                true,
+            ),
+        ] {
+            let segment = error(return_type.clone(), &[])
+                .unwrap_or_else(|| panic!("no error in {}", return_type));
+            assert_eq!(segment.error.ident, "SomeConcreteErr");
+            assert_eq!(segment.boxed, boxed, "{}", return_type);
+        }
+        assert!(error(quote!(-> Option<SomeConcreteErr>), &[]).is_none());
+    }
+
+    #[test]
+    fn wrappers() {
+        for (return_type, expected) in [
+            (quote!(-> Result<T, SomeConcreteErr>), vec![]),
+            (
+                quote!(-> impl Future<Output = Result<T, SomeConcreteErr>>),
+                vec![],
+            ),
+            (
+                quote!(-> Option<Result<T, SomeConcreteErr>>),
+                vec![quote!(::core::option::Option::Some)],
+            ),
+            (
+                quote!(-> ControlFlow<B, Result<T, SomeConcreteErr>>),
+                vec![quote!(::core::ops::ControlFlow::Continue)],
+            ),
+            (
+                quote!(-> Poll<Option<Result<T, SomeConcreteErr>>>),
+                vec![
+                    quote!(::core::task::Poll::Ready),
+                    quote!(::core::option::Option::Some),
+                ],
+            ),
+        ] {
+            let expected = expected
+                .into_iter()
+                .map(|it| syn::parse2::<Path>(it).unwrap())
+                .collect::<Vec<_>>();
+            assert_eq!(
+                expected,
+                error(return_type.clone(), &[]).unwrap().wrappers,
+                "{}",
+                return_type
+            );
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use syn::{parse_quote, Attribute, Field, Fields, Type, TypePath, Variant};
+
+pub fn is_source_attr(attr: &Attribute) -> bool {
+    attr.path().is_ident("source")
+}
+
+pub fn is_from_attr(attr: &Attribute) -> bool {
+    attr.path().is_ident("from")
+}
+
+/// Whether the last segment of `ty` looks like an error, e.g `io::Error` or `ParseIntError`.
+fn looks_like_error(ty: &Type) -> bool {
+    match ty {
+        Type::Path(TypePath { qself: None, path }) => path
+            .segments
+            .last()
+            .is_some_and(|it| it.ident.to_string().ends_with("Error")),
+        Type::Paren(it) => looks_like_error(&it.elem),
+        Type::Group(it) => looks_like_error(&it.elem),
+        _ => false,
+    }
+}
+
+/// The index of the field which is the underlying cause of this variant, following [thiserror]'s rules.
+///
+/// In order, that's a field marked `#[source]` or `#[from]`, a field named `source`,
+/// or (if `by_type`) the first field whose type looks like an error.
+///
+/// [thiserror]: https://docs.rs/thiserror
+pub fn find(fields: &Fields, by_type: bool) -> Option<usize> {
+    let position = |predicate: &dyn Fn(&Field) -> bool| fields.iter().position(predicate);
+    position(&|field| {
+        field
+            .attrs
+            .iter()
+            .any(|it| is_source_attr(it) || is_from_attr(it))
+    })
+    .or_else(|| position(&|field| matches!(&field.ident, Some(it) if it == "source")))
+    .or_else(|| match by_type {
+        true => position(&|field| looks_like_error(&field.ty)),
+        false => None,
+    })
+}
+
+/// Mark fields whose type looks like an error with `#[source]`, for [thiserror].
+///
+/// [thiserror]: https://docs.rs/thiserror
+pub fn mark(variants: &mut [Variant]) {
+    for variant in variants {
+        let already = find(&variant.fields, false).is_some();
+        if let (false, Some(index)) = (already, find(&variant.fields, true)) {
+            let field = variant.fields.iter_mut().nth(index).unwrap();
+            field.attrs.push(parse_quote!(#[source]))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn find_source() {
+        for (variant, by_type, expected) in [
+            (
+                parse_quote!(Io(
+                    #[source]
+                    Wrapped,
+                    io::Error
+                )),
+                false,
+                Some(0),
+            ),
+            (
+                parse_quote!(Io(
+                    u8,
+                    #[from]
+                    io::Error
+                )),
+                false,
+                Some(1),
+            ),
+            (
+                parse_quote!(Io {
+                    a: io::Error,
+                    source: Inner
+                }),
+                false,
+                Some(1),
+            ),
+            (
+                parse_quote!(Io {
+                    a: io::Error,
+                    b: u8
+                }),
+                false,
+                None,
+            ),
+            (
+                parse_quote!(Io(u8, io::Error, ParseIntError)),
+                true,
+                Some(1),
+            ),
+            (parse_quote!(Io(u8, Box<dyn Error>)), true, None),
+            (parse_quote!(Unit), true, None),
+        ] {
+            let variant: Variant = variant;
+            assert_eq!(expected, find(&variant.fields, by_type), "{:?}", variant);
+        }
+    }
+
+    #[test]
+    fn mark_by_type() {
+        let mut variants: [Variant; 3] = [
+            parse_quote!(Io(u8, io::Error)),
+            parse_quote!(Parse(
+                #[from]
+                ParseIntError
+            )),
+            parse_quote!(Wrapped {
+                source: Inner,
+                e: io::Error
+            }),
+        ];
+        mark(&mut variants);
+        let expected: [Variant; 3] = [
+            parse_quote!(Io(
+                u8,
+                #[source]
+                io::Error
+            )),
+            parse_quote!(Parse(
+                #[from]
+                ParseIntError
+            )),
+            parse_quote!(Wrapped {
+                source: Inner,
+                e: io::Error
+            }),
+        ];
+        assert_eq!(expected, variants);
+    }
+}
@@ -0,0 +1,212 @@
+use std::slice;
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{parse_quote, Fields, Generics, Ident, ItemStruct, Variant, Visibility};
+
+use crate::{
+    config::Config,
+    display::{is_display_attr, Message},
+    hidden::Hidden,
+};
+
+/// How the generated impls reach a variant's fields, which depends on whether it wraps a struct.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout<'a> {
+    /// On the enum's variant, like `Self::Variant { .. }`.
+    Inline,
+    /// Through the struct in this module which the enum's variant wraps, like `Self::Variant(module::Variant { .. })`.
+    Wrapped(&'a Ident),
+    /// On the struct itself, like `Self { .. }`, for the struct's own impls.
+    Struct,
+}
+
+impl Layout<'_> {
+    /// A pattern or expression for `variant` with `fields`, like `{ .. }` or `(code)`.
+    pub fn path(self, variant: &Ident, fields: TokenStream) -> TokenStream {
+        match self {
+            Self::Inline => quote!(Self::#variant #fields),
+            Self::Wrapped(module) => quote!(Self::#variant(#module::#variant #fields)),
+            Self::Struct => quote!(Self #fields),
+        }
+    }
+}
+
+/// The module holding the structs for the error called `name`, like `shave_yaks_error` for `ShaveYaksError`.
+///
+/// Each error gets its own, so that structs for variants of the same name don't clash.
+pub fn module(name: &Ident) -> Ident {
+    format_ident!("{}", crate::case::snake_case(name), span = name.span())
+}
+
+/// Implement `Display` and `Error` for each struct, from its variant's message.
+///
+/// This must be called before the enum's implementation takes the messages from the `variants`.
+/// Variants without a valid message are left for the enum's implementation to report.
+pub fn display(generics: &Generics, variants: &[Variant], config: &Config) -> TokenStream {
+    let impls = variants
+        .iter()
+        .filter(|variant| {
+            variant
+                .attrs
+                .iter()
+                .any(|attr| is_display_attr(attr) && attr.parse_args::<Message>().is_ok())
+        })
+        .map(|variant| {
+            let generics = crate::generics::subset(generics, variant);
+            crate::display::implement(
+                &variant.ident,
+                &generics,
+                &mut [variant.clone()],
+                config,
+                Layout::Struct,
+            )
+        });
+    quote!(#(#impls)*)
+}
+
+/// Move the fields of each of `variants` into a struct of the same name, which the variant wraps instead.
+///
+/// Returns the structs, with their hidden field accessors and conversions to and from the enum called `name`,
+/// to be placed in `module` - so `vis` should be as seen from there.
+/// Each struct gets the `derives` and the variant's attributes, though `#[error(..)]` is replaced with
+/// `#[error(transparent)]` on the variant so that it still works with thiserror.
+pub fn split(
+    name: &Ident,
+    module: &Ident,
+    vis: &Visibility,
+    generics: &Generics,
+    derives: &TokenStream,
+    hidden: &[Hidden],
+    variants: &mut [Variant],
+) -> TokenStream {
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let structs = variants.iter_mut().map(|variant| {
+        let ident = &variant.ident;
+        let struct_generics = crate::generics::subset(generics, variant);
+        let accessors = crate::hidden::accessors(
+            ident,
+            vis,
+            &struct_generics,
+            hidden,
+            slice::from_ref(variant),
+            Layout::Struct,
+        );
+
+        let mut fields = variant.fields.clone();
+        for field in fields.iter_mut() {
+            field.vis = vis.clone()
+        }
+        let mut attrs = Vec::new();
+        for attr in std::mem::take(&mut variant.attrs) {
+            if attr.path().is_ident("doc") {
+                variant.attrs.push(attr.clone())
+            } else if attr.path().is_ident("error") {
+                variant.attrs.push(parse_quote!(#[error(transparent)]))
+            }
+            attrs.push(attr)
+        }
+        let semi_token = match fields {
+            Fields::Named(_) => None,
+            Fields::Unnamed(_) | Fields::Unit => Some(Default::default()),
+        };
+        let item = ItemStruct {
+            attrs,
+            vis: vis.clone(),
+            struct_token: Default::default(),
+            ident: ident.clone(),
+            generics: struct_generics.clone(),
+            fields,
+            semi_token,
+        };
+
+        let (_, struct_ty_generics, _) = struct_generics.split_for_impl();
+        let ty = quote!(#ident #struct_ty_generics);
+        variant.fields = Fields::Unnamed(parse_quote!((#module::#ty)));
+        quote! {
+            #derives
+            #item
+
+            #accessors
+
+            impl #impl_generics ::core::convert::From<#ty> for #name #ty_generics #where_clause {
+                fn from(variant: #ty) -> Self {
+                    Self::#ident(variant)
+                }
+            }
+
+            impl #impl_generics ::core::convert::TryFrom<#name #ty_generics> for #ty #where_clause {
+                type Error = #name #ty_generics;
+                fn try_from(error: #name #ty_generics) -> ::core::result::Result<Self, Self::Error> {
+                    match error {
+                        #name::#ident(variant) => ::core::result::Result::Ok(variant),
+                        #[allow(unreachable_patterns)]
+                        other => ::core::result::Result::Err(other),
+                    }
+                }
+            }
+        }
+    });
+    quote!(#(#structs)*)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ident;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn split_variant() {
+        let mut variants: [Variant; 1] = [parse_quote!(
+            #[doc = "Not enough buckets"]
+            #[error("not enough buckets")]
+            NotEnoughBuckets {
+                got: &'a [T],
+                required: usize
+            }
+        )];
+        let split = split(
+            &ident("ShaveYaksError"),
+            &ident("shave_yaks_error"),
+            &parse_quote!(pub),
+            &parse_quote!(<'a, T, U>),
+            &quote!(#[derive(Debug)]),
+            &[],
+            &mut variants,
+        );
+        let expected = quote! {
+            #[derive(Debug)]
+            #[doc = "Not enough buckets"]
+            #[error("not enough buckets")]
+            pub struct NotEnoughBuckets<'a, T> {
+                pub got: &'a [T],
+                pub required: usize
+            }
+
+            impl<'a, T, U> ::core::convert::From<NotEnoughBuckets<'a, T> > for ShaveYaksError<'a, T, U> {
+                fn from(variant: NotEnoughBuckets<'a, T>) -> Self {
+                    Self::NotEnoughBuckets(variant)
+                }
+            }
+
+            impl<'a, T, U> ::core::convert::TryFrom<ShaveYaksError<'a, T, U> > for NotEnoughBuckets<'a, T> {
+                type Error = ShaveYaksError<'a, T, U>;
+                fn try_from(error: ShaveYaksError<'a, T, U>) -> ::core::result::Result<Self, Self::Error> {
+                    match error {
+                        ShaveYaksError::NotEnoughBuckets(variant) => ::core::result::Result::Ok(variant),
+                        #[allow(unreachable_patterns)]
+                        other => ::core::result::Result::Err(other),
+                    }
+                }
+            }
+        };
+        assert_eq!(expected.to_string(), split.to_string());
+        let expected: Variant = parse_quote!(
+            #[doc = "Not enough buckets"]
+            #[error(transparent)]
+            NotEnoughBuckets(shave_yaks_error::NotEnoughBuckets<'a, T>)
+        );
+        assert_eq!(expected, variants[0]);
+    }
+}
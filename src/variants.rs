@@ -0,0 +1,271 @@
+use proc_macro2::TokenStream;
+use quote::ToTokens;
+use syn::{Attribute, Field, Fields, Ident, Variant};
+
+/// The variants collected from `err!` invocations, with repeated definitions merged.
+#[derive(Debug, Default)]
+pub struct Variants {
+    variants: Vec<Variant>,
+}
+
+/// Two definitions of a variant disagree.
+#[derive(Debug)]
+pub struct Conflict {
+    pub message: String,
+    /// The offending part of the new definition.
+    pub here: TokenStream,
+    /// The corresponding part of the first definition.
+    pub first: TokenStream,
+}
+
+impl Variants {
+    /// Add a variant, merging it with any previous definition with the same name.
+    ///
+    /// Attributes need only be given once - they are compared only if both definitions have them.
+    pub fn insert(&mut self, variant: Variant) -> Result<(), Conflict> {
+        let Some(existing) = self
+            .variants
+            .iter_mut()
+            .find(|it| it.ident == variant.ident)
+        else {
+            self.variants.push(variant);
+            return Ok(());
+        };
+        let conflict = |detail: &str, here: &dyn ToTokens, first: &dyn ToTokens| Conflict {
+            message: format!(
+                "conflicting definitions of variant `{}` - {}",
+                variant.ident, detail
+            ),
+            here: here.to_token_stream(),
+            first: first.to_token_stream(),
+        };
+
+        match (&mut existing.fields, &variant.fields) {
+            (Fields::Named(existing_fields), Fields::Named(new_fields))
+                if existing_fields.named.len() == new_fields.named.len() =>
+            {
+                for new_field in &new_fields.named {
+                    let Some(existing_field) = existing_fields
+                        .named
+                        .iter_mut()
+                        .find(|it| it.ident == new_field.ident)
+                    else {
+                        return Err(conflict(
+                            "fields have different names",
+                            new_field,
+                            existing_fields,
+                        ));
+                    };
+                    merge_field(existing_field, new_field, &conflict)?;
+                }
+            }
+            (Fields::Unnamed(existing_fields), Fields::Unnamed(new_fields))
+                if existing_fields.unnamed.len() == new_fields.unnamed.len() =>
+            {
+                for (existing_field, new_field) in
+                    existing_fields.unnamed.iter_mut().zip(&new_fields.unnamed)
+                {
+                    merge_field(existing_field, new_field, &conflict)?;
+                }
+            }
+            (Fields::Unit, Fields::Unit) => {}
+            (existing_fields, new_fields) => {
+                return Err(conflict(
+                    &format!(
+                        "{} here, but {} in the first definition",
+                        describe(new_fields),
+                        describe(existing_fields)
+                    ),
+                    &variant,
+                    &existing.ident,
+                ))
+            }
+        }
+
+        merge_attrs(&mut existing.attrs, &variant.attrs)
+            .map_err(|(here, first)| conflict("attributes differ", &here, &first))?;
+
+        match (&existing.discriminant, &variant.discriminant) {
+            (Some((_, existing_discriminant)), Some((_, new_discriminant)))
+                if existing_discriminant != new_discriminant =>
+            {
+                return Err(conflict(
+                    "discriminants differ",
+                    new_discriminant,
+                    existing_discriminant,
+                ))
+            }
+            (None, Some(_)) => existing.discriminant = variant.discriminant.clone(),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, ident: &Ident) -> Option<&Variant> {
+        self.variants.iter().find(|it| it.ident == *ident)
+    }
+
+    pub fn idents(&self) -> impl Iterator<Item = &Ident> {
+        self.variants.iter().map(|it| &it.ident)
+    }
+
+    pub fn into_inner(self) -> Vec<Variant> {
+        self.variants
+    }
+}
+
+fn merge_field(
+    existing: &mut Field,
+    new: &Field,
+    conflict: &dyn Fn(&str, &dyn ToTokens, &dyn ToTokens) -> Conflict,
+) -> Result<(), Conflict> {
+    if existing.ty != new.ty {
+        let detail = match &new.ident {
+            Some(ident) => format!("field `{}` has a different type", ident),
+            None => String::from("fields have different types"),
+        };
+        return Err(conflict(&detail, &new.ty, &existing.ty));
+    }
+    merge_attrs(&mut existing.attrs, &new.attrs).map_err(|(here, first)| {
+        let detail = match &new.ident {
+            Some(ident) => format!("field `{}` has different attributes", ident),
+            None => String::from("fields have different attributes"),
+        };
+        conflict(&detail, &here, &first)
+    })
+}
+
+fn merge_attrs(
+    existing: &mut Vec<Attribute>,
+    new: &[Attribute],
+) -> Result<(), (TokenStream, TokenStream)> {
+    if existing.is_empty() {
+        existing.extend_from_slice(new)
+    } else if !new.is_empty() && existing != new {
+        return Err((quote::quote!(#(#new)*), quote::quote!(#(#existing)*)));
+    }
+    Ok(())
+}
+
+fn describe(fields: &Fields) -> String {
+    match fields {
+        Fields::Named(it) => format!("{} named field(s)", it.named.len()),
+        Fields::Unnamed(it) => format!("{} unnamed field(s)", it.unnamed.len()),
+        Fields::Unit => String::from("no fields"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use quote::quote;
+    use syn::parse2;
+
+    fn insert_all(
+        variants: impl IntoIterator<Item = TokenStream>,
+    ) -> Result<Vec<Variant>, Conflict> {
+        let mut collected = Variants::default();
+        for variant in variants {
+            collected.insert(parse2(variant).unwrap())?;
+        }
+        Ok(collected.into_inner())
+    }
+
+    fn variants(variants: impl IntoIterator<Item = TokenStream>) -> Vec<Variant> {
+        variants.into_iter().map(|it| parse2(it).unwrap()).collect()
+    }
+
+    #[test]
+    fn merge_identical() {
+        assert_eq!(
+            variants([quote!(Timeout), quote!(Io(std::io::Error))]),
+            insert_all([
+                quote!(Timeout),
+                quote!(Io(std::io::Error)),
+                quote!(Timeout),
+                quote!(Io(std::io::Error)),
+            ])
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn merge_reordered() {
+        assert_eq!(
+            variants([quote!(Foo { a: u8, b: u16 })]),
+            insert_all([quote!(Foo { a: u8, b: u16 }), quote!(Foo { b: u16, a: u8 })]).unwrap()
+        );
+    }
+
+    #[test]
+    fn merge_attributes() {
+        assert_eq!(
+            variants([quote!(
+                #[error("timeout")]
+                Timeout {
+                    #[source]
+                    e: Error
+                }
+            )]),
+            insert_all([
+                quote!(Timeout { e: Error }),
+                quote!(
+                    #[error("timeout")]
+                    Timeout {
+                        #[source]
+                        e: Error
+                    }
+                ),
+                quote!(Timeout { e: Error }),
+            ])
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn conflicts() {
+        for (first, second, here, message) in [
+            (
+                quote!(Foo(u8)),
+                quote!(Foo(u16)),
+                quote!(u16),
+                "conflicting definitions of variant `Foo` - fields have different types",
+            ),
+            (
+                quote!(Foo { a: u8 }),
+                quote!(Foo { a: u16 }),
+                quote!(u16),
+                "conflicting definitions of variant `Foo` - field `a` has a different type",
+            ),
+            (
+                quote!(Foo { a: u8 }),
+                quote!(Foo { b: u8 }),
+                quote!(b: u8),
+                "conflicting definitions of variant `Foo` - fields have different names",
+            ),
+            (
+                quote!(Foo),
+                quote!(Foo(u8)),
+                quote!(Foo(u8)),
+                "conflicting definitions of variant `Foo` - 1 unnamed field(s) here, but no fields in the first definition",
+            ),
+            (
+                quote!(#[error("a")] Foo),
+                quote!(#[error("b")] Foo),
+                quote!(#[error("b")]),
+                "conflicting definitions of variant `Foo` - attributes differ",
+            ),
+            (
+                quote!(Foo = 1),
+                quote!(Foo = 2),
+                quote!(2),
+                "conflicting definitions of variant `Foo` - discriminants differ",
+            ),
+        ] {
+            let conflict = insert_all([first, second]).unwrap_err();
+            assert_eq!(message, conflict.message);
+            assert_eq!(here.to_string(), conflict.here.to_string());
+        }
+    }
+}
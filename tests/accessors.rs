@@ -0,0 +1,52 @@
+#![allow(unused)]
+
+use errgo::errgo;
+
+#[errgo(accessors, location, derive(Debug))]
+fn shave_yaks(num_yaks: usize, empty_buckets: usize) -> Result<(), ShaveYaksError> {
+    match num_yaks {
+        0 => Err(err!(NoYaks)),
+        1 => Err(err!(OneYak(usize = empty_buckets))),
+        _ => Err(err!(NotEnoughBuckets {
+            got = empty_buckets,
+            required: usize = num_yaks
+        })),
+    }
+}
+
+#[errgo(accessors, variant_structs, derive(Debug))]
+fn borrowed<'a>(s: &'a str) -> Result<(), BorrowedError<'a>> {
+    match s {
+        "" => Err(err!(Empty)),
+        s => Err(err!(Bad(&'a str = s, usize = s.len()))),
+    }
+}
+
+#[test]
+fn accessors() {
+    let e = shave_yaks(0, 1).unwrap_err();
+    assert!(e.is_no_yaks());
+    assert!(!e.is_one_yak());
+    assert_eq!(None, e.as_one_yak());
+
+    let e = shave_yaks(1, 3).unwrap_err();
+    assert!(e.is_one_yak());
+    assert_eq!(Some(&3), e.as_one_yak());
+    assert_eq!(None, e.as_not_enough_buckets());
+    assert_eq!(Some(3), e.into_one_yak());
+
+    let e = shave_yaks(4, 3).unwrap_err();
+    assert!(e.is_not_enough_buckets());
+    assert_eq!(Some((&3, &4)), e.as_not_enough_buckets());
+    assert_eq!(Some((3, 4)), e.into_not_enough_buckets());
+}
+
+#[test]
+fn accessors_with_variant_structs() {
+    let e = borrowed("").unwrap_err();
+    assert!(e.is_empty());
+    assert!(!e.is_bad());
+    let e = borrowed("bad").unwrap_err();
+    assert_eq!(Some((&"bad", &3)), e.as_bad());
+    assert_eq!(Some(("bad", 3)), e.into_bad());
+}
@@ -0,0 +1,35 @@
+#![allow(unused)]
+
+use errgo::errgo;
+use std::backtrace::BacktraceStatus;
+
+#[errgo(backtrace, location, derive(Debug, Clone, PartialEq))]
+fn traced(which: u8) -> Result<(), FooError> {
+    match which {
+        0 => Err(err!(Unit)),
+        _ => Err(err!(Tuple(u8 = which))),
+    }
+}
+
+#[errgo(backtrace, display, derive(Debug))]
+fn wrapped(s: &str) -> Result<usize, BarError> {
+    Ok(try_err!(
+        #[display("couldn't parse")]
+        Parse(std::num::ParseIntError),
+        s.parse()
+    )?)
+}
+
+#[test]
+fn backtrace_is_captured() {
+    std::env::set_var("RUST_BACKTRACE", "1");
+    for which in [0, 1] {
+        let e = traced(which).unwrap_err();
+        assert_eq!(e.backtrace().status(), BacktraceStatus::Captured);
+        assert_eq!(e.location().line(), 9 + u32::from(which));
+        assert_eq!(e.clone(), e);
+    }
+    let e = wrapped("x").unwrap_err();
+    assert_eq!(e.backtrace().status(), BacktraceStatus::Captured);
+    assert!(matches!(e, BarError::Parse(_, _)));
+}
@@ -0,0 +1,77 @@
+#![allow(unused)]
+
+use errgo::errgo;
+use std::ops::ControlFlow;
+
+/// Like a C function, which returns `-1` and sets `errno` on failure.
+fn open(path: &str) -> i32 {
+    match std::fs::metadata(path) {
+        Ok(_) => 3,
+        Err(_) => -1,
+    }
+}
+
+struct File;
+
+impl File {
+    fn close(&self, code: i32) -> i32 {
+        code
+    }
+}
+
+#[errgo(default_display, derive(Debug, thiserror::Error))]
+fn wrapper(path: &str) -> Result<i32, WrapperError> {
+    let fd = check!(open(path), Open);
+    check!(File.close(fd - 3), Close);
+    Ok(fd)
+}
+
+#[errgo(derive(Debug))]
+fn next_fd(path: &str) -> Option<Result<i32, NextFdError>> {
+    Some(Ok(check!(open(path), Open)))
+}
+
+#[errgo(derive(Debug))]
+fn visit(path: &str) -> ControlFlow<Result<(), Box<VisitError>>, i32> {
+    ControlFlow::Continue(check!(open(path), Open))
+}
+
+#[test]
+fn check_passes_through_success() {
+    assert_eq!(wrapper(env!("CARGO_MANIFEST_DIR")).unwrap(), 3);
+}
+
+#[test]
+fn check_captures_errno() {
+    let e = wrapper("/does/not/exist").unwrap_err();
+    let WrapperError::Open { call, errno } = e else {
+        panic!("unexpected error {e:?}")
+    };
+    assert_eq!(call, "open");
+    assert_eq!(
+        std::io::Error::from_raw_os_error(errno).kind(),
+        std::io::ErrorKind::NotFound
+    );
+}
+
+#[test]
+fn check_default_message() {
+    let e = WrapperError::Close {
+        call: "close",
+        errno: 9,
+    };
+    assert_eq!(e.to_string(), "close (call: close, errno: 9)");
+}
+
+#[test]
+fn check_returns_through_wrappers() {
+    assert!(matches!(next_fd(env!("CARGO_MANIFEST_DIR")), Some(Ok(3))));
+    assert!(matches!(
+        next_fd("/does/not/exist"),
+        Some(Err(NextFdError::Open { .. }))
+    ));
+    let ControlFlow::Break(Err(e)) = visit("/does/not/exist") else {
+        panic!("expected `Break(Err(..))`")
+    };
+    assert!(matches!(*e, VisitError::Open { .. }));
+}
@@ -0,0 +1,66 @@
+#![allow(unused)]
+
+use errgo::errgo;
+
+const EBUSY: i32 = -16;
+
+#[errgo(code, display, derive(Debug, PartialEq))]
+fn wrap(code: i32) -> Result<(), FfiError> {
+    match code {
+        0 => Ok(()),
+        -22 => Err(err!(
+            #[code = -22]
+            InvalidArg,
+            "invalid argument"
+        )),
+        EBUSY => Err(err!(Busy = EBUSY, "busy")),
+        -1 => Err(err!(
+            #[code = -1]
+            Failed { call: &'static str = "open" },
+            "{call} failed"
+        )),
+        other => Err(FfiError::from_code(other).unwrap_or(FfiError::Unknown(other))),
+    }
+}
+
+#[errgo(code, location, default_display, derive(Debug, thiserror::Error))]
+fn located(code: i32) -> Result<(), LocatedError> {
+    Err(err!(NotFound = -2))
+}
+
+#[errgo(code, derive(Debug, thiserror::Error))]
+fn with_thiserror(code: i32) -> Result<(), ThisError> {
+    Err(err!(
+        #[error("busy")]
+        Busy = EBUSY
+    ))
+}
+
+#[test]
+fn code_round_trips() {
+    for code in [-22, EBUSY, -1, -5] {
+        assert_eq!(wrap(code).unwrap_err().code(), code);
+    }
+    assert_eq!(FfiError::from_code(-22), Some(FfiError::InvalidArg));
+    assert_eq!(FfiError::from_code(EBUSY), Some(FfiError::Busy));
+    assert_eq!(FfiError::from_code(-1), Some(FfiError::Unknown(-1)));
+    assert_eq!(FfiError::from_code(-5), None);
+}
+
+#[test]
+fn code_messages() {
+    assert_eq!(wrap(-1).unwrap_err().to_string(), "open failed");
+    assert_eq!(wrap(-5).unwrap_err().to_string(), "unknown error code -5");
+    assert_eq!(LocatedError::from_code(-3).map(|it| it.to_string()), None);
+    assert_eq!(located(0).unwrap_err().to_string(), "not found");
+    assert_eq!(with_thiserror(0).unwrap_err().to_string(), "busy");
+    assert_eq!(ThisError::Unknown(-5).to_string(), "unknown error code -5");
+}
+
+#[test]
+fn code_with_hidden_fields() {
+    let e = LocatedError::from_code(-2).unwrap();
+    assert!(matches!(e, LocatedError::NotFound { .. }));
+    assert_eq!(e.code(), -2);
+    assert_eq!(e.location().line(), line!() - 3);
+}
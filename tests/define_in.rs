@@ -0,0 +1,100 @@
+#![allow(unused)]
+
+use errgo::errgo;
+
+mod errors {
+    errgo::collect!(
+        super::FooError,
+        super::api::BarError,
+        super::ShaveError,
+        super::SplitError,
+        super::api::QuxError
+    );
+}
+
+use errors::QuxError;
+
+errgo::parent! {
+    #[derive(Debug)]
+    enum CrateError { QuxError }
+}
+
+#[errgo(define_in = errors, location, derive(Debug))]
+fn foo(n: u8) -> Result<u8, FooError> {
+    match n {
+        0 => Err(err!(Zero)),
+        _ => Ok(n),
+    }
+}
+
+mod api {
+    use errgo::errgo;
+
+    #[errgo(define_in = crate::errors, derive(Debug, PartialEq))]
+    pub fn bar(s: &str) -> Result<u8, Box<BarError>> {
+        let parsed = try_err!(Invalid(std::num::ParseIntError), s.parse::<u8>())?;
+        if parsed % 2 == 1 {
+            return Err(err!(Odd { got: u8 = parsed }));
+        }
+        Ok(parsed)
+    }
+
+    #[errgo(define_in = crate::errors, parent = super::CrateError, derive(Debug))]
+    pub fn qux() -> Result<(), QuxError> {
+        Err(err!(Gone))
+    }
+}
+
+#[errgo(define_in = errors, variant_structs, location, derive(Debug))]
+fn split(s: &str) -> Result<u8, SplitError> {
+    match s {
+        "" => Err(err!(Empty {
+            len: usize = s.len()
+        })),
+        s => Ok(try_err!(Parse(std::num::ParseIntError), s.parse())?),
+    }
+}
+
+struct Yak;
+
+#[errgo(define_in = errors, derive(Debug))]
+impl Yak {
+    #[errgo]
+    fn shave(&self, razors: usize) -> Result<(), ShaveError> {
+        match razors {
+            0 => Err(err!(NoRazors)),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[test]
+fn defined_in_module() {
+    let location = foo(0).unwrap_err().location();
+    assert_eq!(file!(), location.file());
+    assert_eq!(25, location.line());
+    assert_eq!(1, foo(1).unwrap());
+
+    assert_eq!(
+        Box::new(errors::BarError::Odd { got: 3 }),
+        api::bar("3").unwrap_err()
+    );
+    assert!(matches!(
+        *api::bar("x").unwrap_err(),
+        errors::BarError::Invalid(_)
+    ));
+
+    let errors::SplitError::Empty(errors::split_error::Empty { len, .. }) = split("").unwrap_err()
+    else {
+        panic!("expected `Empty`")
+    };
+    assert_eq!(0, len);
+    let parse = errors::split_error::Parse::try_from(split("x").unwrap_err()).unwrap();
+    assert_eq!(file!(), parse.location().file());
+
+    assert!(matches!(Yak.shave(0), Err(errors::ShaveError::NoRazors)));
+    assert!(matches!(
+        CrateError::from(api::qux().unwrap_err()),
+        CrateError::Qux(errors::QuxError::Gone)
+    ));
+}
@@ -0,0 +1,165 @@
+use errgo::errgo;
+
+#[errgo(display, derive(Debug))]
+fn message_form(n: usize) -> Result<(), FooError> {
+    if n == 0 {
+        return Err(err!(NoRazors(u8 = 0), "{} razors, needed {}", _0, n));
+    }
+    Err(err!(NotEnoughRazors, "need {} razors", n))
+}
+
+#[errgo(display, derive(Debug))]
+fn attribute_form(u: usize) -> Result<(), BarError> {
+    if u == 0 {
+        return Err(err!(
+            #[display("{0} foos is not enough!")]
+            NotEnoughFoos(usize = u)
+        ));
+    }
+    Err(err!(
+        NotEnoughBuckets {
+            got: usize = u,
+            required: usize = 10,
+        },
+        "not enough buckets - got {got}, needed {required}"
+    ))
+}
+
+#[errgo(display, derive(Debug))]
+fn reused(u: usize) -> Result<(), BazError> {
+    if u == 0 {
+        return Err(err!(Timeout(u)));
+    }
+    Err(err!(Timeout(usize = u), "timed out after {0}s"))
+}
+
+#[errgo(display, derive(Debug))]
+fn empty() -> Result<(), EmptyError> {
+    Ok(())
+}
+
+#[errgo(display, default_display, derive(Debug))]
+fn default_message(got: usize, required: usize) -> Result<(), QuxError> {
+    if got == 0 {
+        return Err(err!(Timeout));
+    }
+    if got == 1 {
+        return Err(err!(TooFew(usize = got), "only {0}"));
+    }
+    Err(err!(NotEnoughBuckets {
+        got: usize = got,
+        required: usize = required,
+    }))
+}
+
+#[errgo(display, source_by_type, derive(Debug))]
+fn sources(which: u8) -> Result<(), SourceError> {
+    let e = std::io::Error::new(std::io::ErrorKind::PermissionDenied, "blah");
+    match which {
+        0 => Err(err!(Io(std::io::Error = e), "io")),
+        1 => Err(err!(
+            Marked(
+                u8 = 1,
+                #[source]
+                std::io::Error = e
+            ),
+            "marked"
+        )),
+        2 => Err(err!(
+            From {
+                #[from]
+                inner: std::io::Error = e
+            },
+            "from"
+        )),
+        _ => Err(err!(Plain(u8 = which), "plain")),
+    }
+}
+
+#[errgo(display, derive(Debug))]
+fn wrapped(s: &str) -> Result<usize, WrappedError> {
+    if s.is_empty() {
+        let empty: Result<usize, std::io::Error> = Err(std::io::Error::other("empty"));
+        return Ok(empty?);
+    }
+    let n = try_err!(
+        #[display("couldn't parse")]
+        Parse(std::num::ParseIntError),
+        s.parse::<usize>()
+    )?;
+    try_err!(
+        #[display("io")]
+        Io(std::io::Error),
+        Ok(())
+    )?;
+    Ok(n)
+}
+
+#[test]
+fn display_message_form() {
+    assert_eq!(message_form(2).unwrap_err().to_string(), "need 2 razors");
+    assert_eq!(
+        message_form(0).unwrap_err().to_string(),
+        "0 razors, needed 0"
+    );
+}
+
+#[test]
+fn display_attribute_form() {
+    assert_eq!(
+        attribute_form(0).unwrap_err().to_string(),
+        "0 foos is not enough!"
+    );
+    assert_eq!(
+        attribute_form(1).unwrap_err().to_string(),
+        "not enough buckets - got 1, needed 10"
+    );
+}
+
+#[test]
+fn display_reused() {
+    assert_eq!(reused(0).unwrap_err().to_string(), "timed out after 0s")
+}
+
+#[test]
+fn display_default_message() {
+    assert_eq!(default_message(0, 5).unwrap_err().to_string(), "timeout");
+    assert_eq!(default_message(1, 5).unwrap_err().to_string(), "only 1");
+    assert_eq!(
+        default_message(3, 5).unwrap_err().to_string(),
+        "not enough buckets (got: 3, required: 5)"
+    );
+}
+
+#[test]
+fn display_source() {
+    use std::error::Error as _;
+    for which in [0, 1, 2] {
+        let source = sources(which).unwrap_err();
+        let source = source.source().unwrap();
+        assert_eq!(source.to_string(), "blah");
+    }
+    assert!(sources(3).unwrap_err().source().is_none());
+}
+
+#[test]
+fn display_wrapped() {
+    use std::error::Error as _;
+    assert_eq!(wrapped("1").unwrap(), 1);
+    let e = wrapped("x").unwrap_err();
+    assert!(matches!(e, WrappedError::Parse(_)));
+    assert_eq!(e.to_string(), "couldn't parse");
+    assert_eq!(
+        e.source().unwrap().to_string(),
+        "invalid digit found in string"
+    );
+    assert!(matches!(wrapped("").unwrap_err(), WrappedError::Io(_)));
+}
+
+#[test]
+fn display_is_error() {
+    fn assert_error(_: &(dyn std::error::Error + Send + Sync + 'static)) {}
+    assert_error(&message_form(1).unwrap_err());
+    let _: fn(EmptyError) -> Box<dyn std::error::Error> = |e| Box::new(e);
+    assert!(empty().is_ok());
+}
@@ -0,0 +1,99 @@
+#![allow(unused)]
+
+use errgo::errgo;
+use std::{io, num::ParseIntError};
+
+#[errgo::extendable]
+#[derive(Debug, PartialEq)]
+pub enum DbError {
+    Timeout,
+    Closed { reason: String },
+}
+
+#[errgo::extendable]
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum UnextendedError {
+    #[error("timeout")]
+    Timeout,
+}
+
+#[errgo(extend, derive(Clone))]
+fn query(sql: &str) -> Result<usize, DbError> {
+    if sql.is_empty() {
+        return Err(DbError::Timeout);
+    }
+    if sql == "close" {
+        return Err(DbError::Closed {
+            reason: String::from("closed"),
+        });
+    }
+    Err(err!(Syntax {
+        sql: String = sql.into()
+    }))
+}
+
+#[errgo(error = ParseError, extend, display)]
+mod parse {
+    #[errgo::extendable]
+    #[derive(Debug)]
+    pub enum ParseError {
+        #[display("empty input")]
+        Empty,
+    }
+
+    pub fn number(s: &str) -> Result<u8, ParseError> {
+        if s.is_empty() {
+            return Err(ParseError::Empty);
+        }
+        let parsed = try_err!(
+            #[display("invalid number")]
+            Invalid(std::num::ParseIntError),
+            s.parse::<u8>()
+        )?;
+        Ok(parsed)
+    }
+
+    pub fn even(s: &str) -> Result<u8, ParseError> {
+        match number(s)? {
+            even if even % 2 == 0 => Ok(even),
+            odd => Err(err!(Odd(u8 = odd), "{0} is odd")),
+        }
+    }
+}
+
+#[test]
+fn variants_are_added() {
+    assert_eq!(Err(DbError::Timeout), query(""));
+    assert_eq!(
+        Err(DbError::Syntax {
+            sql: String::from("drop")
+        }),
+        query("drop")
+    );
+    let cloned = query("close").unwrap_err().clone();
+    assert_eq!(
+        DbError::Closed {
+            reason: String::from("closed")
+        },
+        cloned
+    );
+}
+
+#[test]
+fn unextended_is_defined() {
+    assert_eq!("timeout", UnextendedError::Timeout.to_string());
+}
+
+#[test]
+fn extended_from_module() {
+    use std::error::Error as _;
+    assert_eq!("empty input", parse::even("").unwrap_err().to_string());
+    assert_eq!("3 is odd", parse::even("3").unwrap_err().to_string());
+    let invalid = parse::even("x").unwrap_err();
+    assert_eq!("invalid number", invalid.to_string());
+    assert!(invalid.source().is_some());
+    assert!(matches!(
+        parse::ParseError::from("x".parse::<u8>().unwrap_err()),
+        parse::ParseError::Invalid(_)
+    ));
+}
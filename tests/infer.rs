@@ -0,0 +1,48 @@
+#![allow(unused)]
+
+use errgo::errgo;
+
+#[errgo(derive(Debug, PartialEq))]
+fn shave_yaks(num_yaks: usize, empty_buckets: usize) -> Result<(), ShaveYaksError> {
+    if num_yaks > empty_buckets {
+        return Err(err!(NotEnoughBuckets {
+            got = empty_buckets,
+            num_yaks,
+        }));
+    }
+    Ok(())
+}
+
+#[errgo(derive(Debug, PartialEq))]
+fn borrowed<'a, T: Clone>(name: &'a str, mut payload: T) -> Result<(), BorrowedError<'a, T>> {
+    Err(err!(Named { name, payload }))
+}
+
+struct Yak;
+
+#[errgo(derive(Debug, PartialEq))]
+impl Yak {
+    #[errgo]
+    fn shave(&self, razors: u8) -> Result<(), ShaveError> {
+        Err(err!(NotEnoughRazors { razors }))
+    }
+}
+
+#[test]
+fn types_are_inferred() {
+    assert_eq!(
+        Err(ShaveYaksError::NotEnoughBuckets {
+            got: 1,
+            num_yaks: 2
+        }),
+        shave_yaks(2, 1)
+    );
+    assert_eq!(
+        Err(BorrowedError::Named {
+            name: "yak",
+            payload: 1
+        }),
+        borrowed("yak", 1)
+    );
+    assert_eq!(Err(ShaveError::NotEnoughRazors { razors: 0 }), Yak.shave(0));
+}
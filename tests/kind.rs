@@ -0,0 +1,71 @@
+#![allow(unused)]
+
+use errgo::errgo;
+use std::collections::HashSet;
+
+#[errgo(kind, code, derive(Debug))]
+fn shave_yaks(num_yaks: usize, empty_buckets: usize) -> Result<(), ShaveYaksError> {
+    match num_yaks {
+        0 => Err(err!(NoYaks = -1)),
+        _ => Err(err!(
+            #[code = -2]
+            NotEnoughBuckets {
+                got = empty_buckets,
+                required: usize = num_yaks
+            }
+        )),
+    }
+}
+
+#[errgo(kind, variant_structs, location, derive(Debug))]
+fn borrowed<'a>(s: &'a str) -> Result<(), BorrowedError<'a>> {
+    match s {
+        "" => Err(err!(Empty)),
+        s => Err(err!(Bad(&'a str = s))),
+    }
+}
+
+#[errgo::extendable]
+#[derive(Debug)]
+pub enum DbError {
+    Timeout,
+}
+
+#[errgo(extend, kind)]
+fn query(sql: &str) -> Result<(), DbError> {
+    match sql {
+        "" => Err(DbError::Timeout),
+        _ => Err(err!(Syntax {
+            sql: String = sql.into()
+        })),
+    }
+}
+
+#[test]
+fn kinds() {
+    assert_eq!(
+        ShaveYaksErrorKind::NoYaks,
+        shave_yaks(0, 1).unwrap_err().kind()
+    );
+    let kind = shave_yaks(2, 1).unwrap_err().kind();
+    assert_eq!(ShaveYaksErrorKind::NotEnoughBuckets, kind);
+    assert_eq!("NotEnoughBuckets", format!("{kind:?}"));
+    assert_eq!(
+        ShaveYaksErrorKind::Unknown,
+        ShaveYaksError::Unknown(3).kind()
+    );
+    let kinds = [
+        ShaveYaksErrorKind::NoYaks,
+        ShaveYaksErrorKind::NoYaks,
+        ShaveYaksErrorKind::Unknown,
+    ]
+    .into_iter()
+    .collect::<HashSet<_>>();
+    assert_eq!(2, kinds.len());
+
+    assert_eq!(BorrowedErrorKind::Empty, borrowed("").unwrap_err().kind());
+    assert_eq!(BorrowedErrorKind::Bad, borrowed("x").unwrap_err().kind());
+
+    assert_eq!(DbErrorKind::Timeout, query("").unwrap_err().kind());
+    assert_eq!(DbErrorKind::Syntax, query("x").unwrap_err().kind());
+}
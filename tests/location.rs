@@ -0,0 +1,71 @@
+#![allow(unused)]
+
+use errgo::errgo;
+
+#[errgo(location, derive(Debug, Clone, PartialEq, Eq, Hash))]
+fn located(which: u8) -> Result<(), FooError> {
+    match which {
+        0 => Err(err!(Unit)),
+        1 => Err(err!(Tuple(u8 = which))),
+        2 => Err(err!(Named { which: u8 = which })),
+        _ => Err(err!(Unit)),
+    }
+}
+
+#[errgo(location, display, derive(Debug))]
+fn wrapped(s: &str) -> Result<usize, BarError> {
+    if s.is_empty() {
+        let empty: Result<usize, std::num::ParseIntError> = "".parse();
+        return Ok(empty?);
+    }
+    let parsed = try_err!(
+        #[display("couldn't parse {0}")]
+        Parse(std::num::ParseIntError),
+        s.parse()
+    )?;
+    Ok(parsed)
+}
+
+#[test]
+fn location_is_recorded() {
+    for (which, line) in [(0, 8), (1, 9), (2, 10), (3, 11)] {
+        let location = located(which).unwrap_err().location();
+        assert_eq!(location.file(), file!());
+        assert_eq!(location.line(), line);
+        assert_eq!(location.column(), 18);
+    }
+}
+
+#[test]
+fn location_is_ignored_by_derives() {
+    assert_eq!(located(0).unwrap_err(), located(3).unwrap_err());
+    assert_ne!(located(0).unwrap_err(), located(1).unwrap_err());
+}
+
+#[test]
+fn location_of_conversions() {
+    let e = wrapped("").unwrap_err();
+    assert_eq!(e.location().line(), 19);
+    assert_eq!(
+        e.to_string(),
+        "couldn't parse cannot parse integer from empty string"
+    );
+    assert_eq!(wrapped("x").unwrap_err().location().line(), 21);
+}
+
+#[errgo(location, display, default_display, derive(Debug))]
+fn described(got: usize) -> Result<(), BazError> {
+    if got == 0 {
+        return Err(err!(NotEnoughBuckets { got: usize = got }));
+    }
+    Err(err!(NotEnoughBuckets { got: got }))
+}
+
+#[test]
+fn location_is_left_out_of_messages() {
+    assert_eq!(
+        described(1).unwrap_err().to_string(),
+        "not enough buckets (got: 1)"
+    );
+    assert_eq!(described(0).unwrap_err().location().line(), 59);
+}
@@ -0,0 +1,42 @@
+#![allow(unused)]
+
+use errgo::errgo;
+
+#[errgo(derive(Debug, PartialEq))]
+fn insert(key: &str) -> Result<(), InsertError> {
+    let id = key.len() as u64;
+    let name = key.to_uppercase();
+    let bytes = key.as_bytes().to_vec();
+    if id > 3 {
+        return Err(err!(Conflict {
+            id: u64,
+            name: String,
+            bytes: Vec<u8>,
+        }));
+    }
+    Err(err!(Short { id: u64 }))
+}
+
+#[errgo(derive(Debug, PartialEq))]
+fn reused(len: usize) -> Result<(), ReusedError> {
+    let count = len + 1;
+    if len == 0 {
+        // `Empty`'s types are given below, so `count` is the value
+        return Err(err!(Empty { len: count }));
+    }
+    Err(err!(Empty { len: usize = len }))
+}
+
+#[test]
+fn shorthand_takes_locals() {
+    assert_eq!(
+        Err(InsertError::Conflict {
+            id: 4,
+            name: String::from("ABCD"),
+            bytes: b"abcd".to_vec(),
+        }),
+        insert("abcd")
+    );
+    assert_eq!(Err(InsertError::Short { id: 1 }), insert("a"));
+    assert_eq!(Err(ReusedError::Empty { len: 1 }), reused(0));
+}
@@ -29,6 +29,21 @@ fn error_implements_from() -> Result<(), BazError> {
     ))
 }
 
+#[errgo(default_display, derive(Debug, thiserror::Error))]
+fn default_message(u: usize) -> Result<(), QuxError> {
+    Err(err!(HTTPFailed(u16 = 404, &'static str = "not found")))
+}
+
+#[errgo(source_by_type, derive(Debug, thiserror::Error))]
+fn source_by_type(u: &str) -> Result<usize, ParseError> {
+    u.parse().map_err(|e| {
+        err!(
+            #[error("bad number")]
+            Parse(std::num::ParseIntError = e)
+        )
+    })
+}
+
 #[test]
 fn thiserror_message_simple() {
     assert_eq!(simple_string_error().unwrap_err().to_string(), "no bars :(")
@@ -42,6 +57,24 @@ fn thiserror_message_interpolated() {
     )
 }
 
+#[test]
+fn thiserror_message_default() {
+    assert_eq!(
+        default_message(1).unwrap_err().to_string(),
+        "http failed (404, not found)"
+    )
+}
+
+#[test]
+fn thiserror_source_by_type() {
+    use std::error::Error as _;
+    let e = source_by_type("x").unwrap_err();
+    assert_eq!(
+        e.source().unwrap().to_string(),
+        "invalid digit found in string"
+    )
+}
+
 fn assert_error_implements_from(e: std::io::Error) -> BazError {
     e.into()
 }
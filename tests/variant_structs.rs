@@ -0,0 +1,147 @@
+#![allow(unused)]
+
+use errgo::errgo;
+use std::error::Error as _;
+
+#[errgo(variant_structs, display, location, derive(Debug, PartialEq))]
+fn shave_yaks(num_yaks: usize, empty_buckets: usize) -> Result<usize, ShaveYaksError> {
+    if num_yaks == 0 {
+        return Err(err!(NoYaks, "no yaks"));
+    }
+    if num_yaks > empty_buckets {
+        return Err(err!(
+            NotEnoughBuckets {
+                got = empty_buckets,
+                required: usize = num_yaks
+            },
+            "need {required} buckets, got {got}"
+        ));
+    }
+    let parsed = try_err!(
+        #[display("couldn't parse")]
+        Parse(std::num::ParseIntError),
+        "x".parse::<usize>()
+    )?;
+    Ok(parsed)
+}
+
+#[errgo(variant_structs, derive(Debug, thiserror::Error))]
+fn borrowed<'a, T: std::fmt::Debug>(s: &'a str, t: T) -> Result<T, BorrowedError<'a, T>> {
+    if s.is_empty() {
+        return Err(err!(
+            #[error("empty")]
+            Empty
+        ));
+    }
+    if s == "t" {
+        return Err(err!(
+            #[error("{0:?}")]
+            Payload(T = t)
+        ));
+    }
+    Err(err!(
+        #[error("bad {s}")]
+        Bad { s: &'a str = s }
+    ))
+}
+
+#[errgo(variant_structs, code, display, derive(Debug, PartialEq))]
+fn ffi(code: i32) -> Result<(), FfiError> {
+    match code {
+        -22 => Err(err!(InvalidArg = -22, "invalid argument")),
+        other => {
+            Err(FfiError::from_code(other).unwrap_or(FfiError::Unknown(ffi_error::Unknown(other))))
+        }
+    }
+}
+
+#[test]
+fn variants_wrap_structs() {
+    let e = shave_yaks(2, 1).unwrap_err();
+    assert_eq!("need 2 buckets, got 1", e.to_string());
+    assert_eq!(12, e.location().line());
+    let buckets = shave_yaks_error::NotEnoughBuckets::try_from(e).unwrap();
+    assert_eq!((1, 2), (buckets.got, buckets.required));
+    assert_eq!("need 2 buckets, got 1", buckets.to_string());
+    assert_eq!(12, buckets.location().line());
+
+    let e = shave_yaks(0, 1).unwrap_err();
+    assert_eq!("no yaks", e.to_string());
+    let e = shave_yaks_error::NotEnoughBuckets::try_from(e).unwrap_err();
+    assert!(matches!(
+        e,
+        ShaveYaksError::NoYaks(shave_yaks_error::NoYaks { .. })
+    ));
+
+    let e = shave_yaks(1, 1).unwrap_err();
+    assert!(e.source().is_some());
+    let ShaveYaksError::Parse(parse) = e else {
+        panic!("expected `Parse`")
+    };
+    assert_eq!("couldn't parse", parse.to_string());
+    assert!(parse.source().is_some());
+}
+
+#[test]
+fn structs_convert_into_the_enum() {
+    fn buckets(got: usize) -> Result<(), shave_yaks_error::NotEnoughBuckets> {
+        match shave_yaks(2, got) {
+            Err(e) => Err(e.try_into().expect("not enough buckets")),
+            Ok(_) => Ok(()),
+        }
+    }
+    fn any() -> Result<(), ShaveYaksError> {
+        buckets(1)?;
+        Ok(())
+    }
+    assert!(matches!(any(), Err(ShaveYaksError::NotEnoughBuckets(_))));
+}
+
+#[test]
+fn generic_structs() {
+    assert_eq!("empty", borrowed("", 1).unwrap_err().to_string());
+    assert_eq!("bad x", borrowed("x", 1).unwrap_err().to_string());
+    let payload: borrowed_error::Payload<u8> = borrowed("t", 7).unwrap_err().try_into().unwrap();
+    assert_eq!(7, payload.0);
+    assert_eq!("7", payload.to_string());
+    let bad: borrowed_error::Bad<'_> = borrowed("y", 7).unwrap_err().try_into().unwrap();
+    assert_eq!("y", bad.s);
+}
+
+#[test]
+fn codes() {
+    let e = ffi(-22).unwrap_err();
+    assert_eq!(-22, e.code());
+    assert_eq!(
+        Some(FfiError::InvalidArg(ffi_error::InvalidArg)),
+        FfiError::from_code(-22)
+    );
+    assert_eq!(
+        FfiError::Unknown(ffi_error::Unknown(-5)),
+        ffi(-5).unwrap_err()
+    );
+    assert_eq!(-5, ffi(-5).unwrap_err().code());
+}
+
+#[errgo(variant_structs, display, derive(Debug))]
+fn find_user(id: u32) -> Result<(), FindUserError> {
+    Err(err!(NotFound { id }, "no user {id}"))
+}
+
+#[errgo(variant_structs, display, derive(Debug))]
+fn find_group(name: &str) -> Result<(), FindGroupError> {
+    Err(err!(
+        NotFound {
+            name: String = name.into()
+        },
+        "no group {name}"
+    ))
+}
+
+#[test]
+fn errors_share_variant_names() {
+    let user: find_user_error::NotFound = find_user(1).unwrap_err().try_into().unwrap();
+    assert_eq!("no user 1", user.to_string());
+    let group: find_group_error::NotFound = find_group("x").unwrap_err().try_into().unwrap();
+    assert_eq!("no group x", group.to_string());
+}
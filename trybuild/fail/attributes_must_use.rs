@@ -0,0 +1,12 @@
+#![deny(unused_must_use)]
+
+use errgo::errgo;
+
+#[errgo(attributes(#[must_use = "errors must be handled"]))]
+fn foo() -> Result<(), FooError> {
+    Err(err!(Bar))
+}
+
+fn main() {
+    foo().unwrap_err();
+}
@@ -0,0 +1,13 @@
+use errgo::errgo;
+
+#[errgo(backtrace(provide))]
+fn foo() -> Result<(), FooError> {
+    Err(err!(Foo))
+}
+
+#[errgo(backtrace(capture))]
+fn bar() -> Result<(), BarError> {
+    Err(err!(Bar))
+}
+
+fn main() {}
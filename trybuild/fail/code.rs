@@ -0,0 +1,25 @@
+use errgo::errgo;
+
+#[errgo(code)]
+fn foo(code: i32) -> Result<(), FooError> {
+    if code == -1 {
+        return Err(err!(#[code = -1] Failed));
+    }
+    Err(err!(Other))
+}
+
+#[errgo(code)]
+fn bar() -> Result<(), BarError> {
+    Err(err!(#[code(1)] Failed))
+}
+
+#[errgo(code)]
+fn baz(code: i32) -> Result<(), BazError> {
+    match code {
+        -1 => Err(err!(#[code = -1] Failed)),
+        -2 => Err(err!(Busy = -1)),
+        _ => Err(err!(Unknown { code: i32 = code })),
+    }
+}
+
+fn main() {}
@@ -0,0 +1,22 @@
+use errgo::errgo;
+
+mod errors {}
+
+#[errgo(error = LibError, define_in = errors)]
+mod lib {
+    pub fn open() -> Result<(), LibError> {
+        Err(err!(Busy))
+    }
+}
+
+#[errgo::extendable]
+pub enum DbError {
+    Timeout,
+}
+
+#[errgo(extend, define_in = errors)]
+fn query() -> Result<(), DbError> {
+    Err(err!(Syntax))
+}
+
+fn main() {}
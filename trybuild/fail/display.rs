@@ -0,0 +1,22 @@
+use errgo::errgo;
+
+#[errgo(display, derive(Debug))]
+fn no_message() -> Result<(), FooError> {
+    Err(err!(Timeout))
+}
+
+#[errgo(display)]
+fn not_debug() -> Result<(), BazError> {
+    Err(err!(Timeout, "timed out"))
+}
+
+#[errgo(display, derive(Debug))]
+fn message_args(n: usize) -> Result<(), QuxError> {
+    let needed = n + 1;
+    if n == 0 {
+        return Err(err!(NotEnoughRazors, "need {} razors", needed));
+    }
+    Err(err!(NotEnoughBuckets, "need {} buckets", n + 1))
+}
+
+fn main() {}
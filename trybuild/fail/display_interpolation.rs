@@ -0,0 +1,8 @@
+use errgo::errgo;
+
+#[errgo(display, derive(Debug))]
+fn bad_field(u: usize) -> Result<(), BarError> {
+    Err(err!(Timeout { after: usize = u }, "timed out after {before}s"))
+}
+
+fn main() {}
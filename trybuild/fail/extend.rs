@@ -0,0 +1,34 @@
+use errgo::errgo;
+
+#[errgo::extendable]
+#[derive(Debug)]
+pub enum DbError {
+    Timeout,
+}
+
+#[errgo(extend)]
+fn query() -> Result<(), DbError> {
+    Err(err!(Timeout))
+}
+
+#[errgo::extendable]
+enum LocatedError {
+    Timeout,
+}
+
+#[errgo(extend, location)]
+fn located() -> Result<(), LocatedError> {
+    Err(err!(Other))
+}
+
+#[errgo::extendable]
+enum StructError {
+    Timeout,
+}
+
+#[errgo(extend, variant_structs)]
+fn structs() -> Result<(), StructError> {
+    Err(err!(Other))
+}
+
+fn main() {}
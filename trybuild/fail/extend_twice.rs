@@ -0,0 +1,19 @@
+use errgo::errgo;
+
+#[errgo::extendable]
+#[derive(Debug)]
+pub enum DbError {
+    Timeout,
+}
+
+#[errgo(extend)]
+fn query() -> Result<(), DbError> {
+    Err(err!(Syntax))
+}
+
+#[errgo(extend)]
+fn again() -> Result<(), DbError> {
+    Err(err!(Again))
+}
+
+fn main() {}
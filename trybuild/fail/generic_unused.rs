@@ -0,0 +1,13 @@
+use errgo::errgo;
+
+#[errgo]
+fn unused<'a, T>(input: &'a str) -> Result<(), UnusedError<'a, T>> {
+    Err(err!(Unexpected { token: &'a str = input }))
+}
+
+#[errgo]
+fn unknown<T>() -> Result<(), UnknownError<U>> {
+    Ok(())
+}
+
+fn main() {}
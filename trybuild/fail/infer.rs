@@ -0,0 +1,18 @@
+use errgo::errgo;
+
+#[errgo]
+fn not_a_param(got: usize) -> Result<(), FooError> {
+    Err(err!(Short { len = got + 1 }))
+}
+
+#[errgo]
+fn elided(name: &str) -> Result<(), BarError> {
+    Err(err!(Unnamed { name }))
+}
+
+#[errgo]
+fn missing() -> Result<(), BazError> {
+    Err(err!(Missing { nowhere }))
+}
+
+fn main() {}
@@ -0,0 +1,6 @@
+use errgo::errgo;
+
+#[errgo]
+struct Foo;
+
+fn main() {}
@@ -0,0 +1,10 @@
+use errgo::errgo;
+
+#[errgo]
+mod unnamed {
+    fn foo() -> Result<(), FooError> {
+        Err(err!(Foo))
+    }
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+use errgo::errgo;
+
+errgo::parent! {
+    enum CrateError { ParseError }
+}
+
+#[errgo(parent = CrateError)]
+fn parse<'a>(input: &'a str) -> Result<(), ParseError<'a>> {
+    Err(err!(Unexpected(&'a str = input)))
+}
+
+fn main() {}
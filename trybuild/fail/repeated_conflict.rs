@@ -0,0 +1,29 @@
+use errgo::errgo;
+
+#[errgo]
+fn field_types(u: usize) -> Result<(), FooError> {
+    if u == 0 {
+        return Err(err!(Structy { u: usize = u }));
+    }
+    Err(err!(Structy { u: u8 = 0 }))
+}
+
+#[errgo]
+fn shape() -> Result<(), BarError> {
+    let _ = err!(Timeout);
+    Err(err!(Timeout(usize = 1)))
+}
+
+#[errgo]
+fn attributes() -> Result<(), BazError> {
+    let _ = err!(
+        #[allow(unused)]
+        Timeout
+    );
+    Err(err!(
+        #[deny(unused)]
+        Timeout
+    ))
+}
+
+fn main() {}
@@ -0,0 +1,33 @@
+use errgo::errgo;
+
+#[errgo]
+fn foo(u: usize) -> Result<(), FooError> {
+    if u == 0 {
+        return Err(err!(Declared(usize = u)));
+    }
+    if u == 1 {
+        return Err(err!(Other));
+    }
+    Err(err!(Undeclared(u)))
+}
+
+#[errgo]
+fn bar(u: usize) -> Result<(), BarError> {
+    Err(err!(Undeclared { u: u }))
+}
+
+#[errgo]
+fn baz(u: usize) -> Result<(), BazError> {
+    if u == 0 {
+        return Err(err!(Declared { u: usize = u }));
+    }
+    Err(err!(Declared { v: u }))
+}
+
+#[errgo]
+fn qux(u: usize) -> Result<(), QuxError> {
+    let count = u + 1;
+    Err(err!(Undeclared { n: count }))
+}
+
+fn main() {}
@@ -0,0 +1,28 @@
+use errgo::errgo;
+use std::io;
+
+fn op() -> Result<(), io::Error> {
+    Ok(())
+}
+
+#[errgo]
+fn same_type() -> Result<(), FooError> {
+    try_err!(Read(io::Error), op())?;
+    try_err!(Write(io::Error), op())?;
+    Ok(())
+}
+
+#[errgo]
+fn undeclared() -> Result<(), BarError> {
+    try_err!(Read, op())?;
+    Ok(())
+}
+
+#[errgo]
+fn not_wrapping() -> Result<(), BazError> {
+    let _ = err!(Read { e: io::Error = io::Error::other("") });
+    try_err!(Read, op())?;
+    Ok(())
+}
+
+fn main() {}
@@ -0,0 +1,42 @@
+use errgo::errgo;
+use std::{future::Future, pin::Pin};
+
+#[errgo(derive(Debug))]
+async fn plain(fail: bool) -> Result<(), PlainError> {
+    if fail {
+        return Err(err!(Failed));
+    }
+    let nested = async { Err::<(), _>(err!(InBlock { code: i32 = 1 })) };
+    nested.await
+}
+
+#[errgo(derive(Debug))]
+fn future(fail: bool) -> impl Future<Output = Result<(), FutureError>> + Send {
+    async move {
+        match fail {
+            true => Err(err!(Failed)),
+            false => Ok(()),
+        }
+    }
+}
+
+#[errgo(derive(Debug))]
+fn boxed(fail: bool) -> Pin<Box<dyn Future<Output = Result<(), BoxedError>> + Send>> {
+    Box::pin(async move {
+        match fail {
+            true => Err(err!(Failed(String = String::from("boxed")))),
+            false => Ok(()),
+        }
+    })
+}
+
+fn assert_send_sync<T: Send + Sync>() {}
+
+fn main() {
+    assert_send_sync::<PlainError>();
+    assert_send_sync::<FutureError>();
+    assert_send_sync::<BoxedError>();
+    let _ = plain(true);
+    let _ = future(true);
+    let _ = boxed(true);
+}
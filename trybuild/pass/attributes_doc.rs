@@ -0,0 +1,18 @@
+//! A crate with documentation
+#![deny(missing_docs)]
+
+use errgo::errgo;
+
+/// Foo the bar.
+#[errgo(
+    visibility(pub),
+    attributes(#[doc = "The ways [`foo`] can fail."], #[non_exhaustive])
+)]
+pub fn foo() -> Result<(), FooError> {
+    Err(err!(
+        /// No bars.
+        Bar
+    ))
+}
+
+fn main() {}
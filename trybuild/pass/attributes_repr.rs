@@ -0,0 +1,12 @@
+use errgo::errgo;
+
+#[errgo(attributes(#[repr(u32)]))]
+fn foo() -> Result<(), FooError> {
+    Err(err!(Bar))?;
+    Err(err!(Baz))?;
+    Ok(())
+}
+
+const _: () = assert!(std::mem::size_of::<FooError>() == 4);
+
+fn main() {}
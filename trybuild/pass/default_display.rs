@@ -0,0 +1,9 @@
+use errgo::errgo;
+
+// Without `display` or thiserror, there's nothing to read the messages
+#[errgo(default_display, derive(Debug))]
+fn foo(n: u8) -> Result<(), FooError> {
+    Err(err!(Bar { n }))
+}
+
+fn main() {}
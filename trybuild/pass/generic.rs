@@ -0,0 +1,57 @@
+use errgo::errgo;
+use std::fmt::Debug;
+
+#[errgo(derive(Debug))]
+fn parse<'a, T>(input: &'a str, payload: T) -> Result<(), ParseError<'a, T>>
+where
+    T: Debug,
+{
+    if input.is_empty() {
+        return Err(err!(Empty));
+    }
+    if input.starts_with('!') {
+        return Err(err!(Payload(T = payload)));
+    }
+    Err(err!(Unexpected { token: &'a str = input }))
+}
+
+struct Parser<'a> {
+    input: &'a str,
+}
+
+#[errgo]
+impl<'a> Parser<'a> {
+    #[errgo]
+    fn next<const N: usize>(&self) -> Result<(), NextError<'a, N>> {
+        Err(err!(Unexpected {
+            token: &'a str = self.input,
+            expected: [u8; N] = [0; N]
+        }))
+    }
+}
+
+fn assert_debug(_: impl Debug) {}
+
+fn assert_parse_error(e: ParseError<'_, usize>) {
+    assert_debug(&e);
+    match e {
+        ParseError::Empty => (),
+        ParseError::Payload(u) => {
+            let _: usize = u;
+        }
+        ParseError::Unexpected { token } => {
+            let _: &str = token;
+        }
+    }
+}
+
+fn assert_next_error(e: NextError<'_, 1>) {
+    match e {
+        NextError::Unexpected { token, expected } => {
+            let _: &str = token;
+            let _: [u8; 1] = expected;
+        }
+    }
+}
+
+fn main() {}
@@ -0,0 +1,41 @@
+use errgo::errgo;
+
+struct Yak;
+
+#[errgo(derive(Clone, Copy))]
+impl Yak {
+    #[errgo]
+    fn shave(&self, num_razors: usize) -> Result<(), ShaveError> {
+        if num_razors == 0 {
+            return Err(err!(NotEnoughRazors));
+        }
+        Ok(())
+    }
+
+    #[errgo(derive(Debug))]
+    pub fn feed(&self) -> Result<(), FeedError> {
+        Err(err!(NoFood(usize = 0)))
+    }
+
+    fn untouched(&self) {}
+}
+
+fn assert_copy(_: impl Copy) {}
+fn assert_debug(_: impl std::fmt::Debug) {}
+
+fn assert_shave_error(e: ShaveError) {
+    assert_copy(e);
+    match e {
+        ShaveError::NotEnoughRazors => (),
+    }
+}
+
+fn assert_feed_error(e: FeedError) {
+    assert_copy(e);
+    assert_debug(e);
+    match e {
+        FeedError::NoFood(_) => (),
+    }
+}
+
+fn main() {}
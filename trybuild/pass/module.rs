@@ -0,0 +1,42 @@
+use errgo::errgo;
+
+#[errgo(error = LibError, derive(Debug, PartialEq))]
+mod lib {
+    pub struct Handle;
+
+    pub fn open(path: &str) -> Result<Handle, LibError> {
+        if path.is_empty() {
+            return Err(err!(InvalidArg));
+        }
+        Ok(Handle)
+    }
+
+    pub fn close(handle: Handle, code: i32) -> Result<(), LibError> {
+        if code < 0 {
+            return Err(err!(Failed(i32 = code)));
+        }
+        let _ = handle;
+        Err(err!(InvalidArg))
+    }
+
+    impl Handle {
+        pub fn read(&self, code: i32) -> Result<(), LibError> {
+            Err(err!(Failed(code)))
+        }
+    }
+
+    #[errgo::errgo]
+    pub fn separate() -> Result<(), SeparateError> {
+        Err(err!(Other))
+    }
+}
+
+fn main() {
+    assert_eq!(lib::open("").err(), Some(lib::LibError::InvalidArg));
+    assert_eq!(
+        lib::Handle.read(-1).unwrap_err(),
+        lib::LibError::Failed(-1)
+    );
+    let _ = lib::close(lib::Handle, 0);
+    let _: lib::SeparateError = lib::separate().unwrap_err();
+}
@@ -0,0 +1,45 @@
+use errgo::errgo;
+
+errgo::parent! {
+    #[derive(Debug, thiserror::Error)]
+    pub enum CrateError {
+        #[error(transparent)]
+        OpenError,
+        #[error(transparent)]
+        Close,
+        #[error("other: {0}")]
+        Other(String),
+        #[own]
+        #[error("cancelled")]
+        Cancelled,
+    }
+}
+
+#[errgo(parent = CrateError, derive(Debug, thiserror::Error))]
+fn open(fail: bool) -> Result<(), OpenError> {
+    match fail {
+        true => Err(err!(#[error("busy")] Busy)),
+        false => Ok(()),
+    }
+}
+
+#[errgo(parent = crate::CrateError, derive(Debug, thiserror::Error))]
+fn close() -> Result<(), Close> {
+    Err(err!(#[error("gone")] Gone))
+}
+
+fn api() -> Result<(), CrateError> {
+    open(false)?;
+    close()?;
+    Ok(())
+}
+
+fn main() {
+    assert!(matches!(api(), Err(CrateError::Close(Close::Gone))));
+    assert!(matches!(
+        CrateError::from(open(true).unwrap_err()),
+        CrateError::Open(OpenError::Busy)
+    ));
+    assert_eq!(CrateError::Other("x".into()).to_string(), "other: x");
+    assert_eq!(CrateError::Cancelled.to_string(), "cancelled");
+}
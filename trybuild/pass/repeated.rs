@@ -0,0 +1,32 @@
+use errgo::errgo;
+
+#[errgo(derive(Debug, PartialEq))]
+fn foo(u: usize) -> Result<(), FooError> {
+    if u == 0 {
+        return Err(err!(Timeout));
+    }
+    if u == 1 {
+        return Err(err!(Timeout));
+    }
+    if u == 2 {
+        return Err(err!(Tuply(usize = u, char = 'a')));
+    }
+    if u == 3 {
+        return Err(err!(
+            #[allow(unused)]
+            Structy { u: usize = u }
+        ));
+    }
+    Err(err!(Tuply(usize = 0, char = 'b')))?;
+    Err(err!(Structy { u: usize = 0 }))
+}
+
+fn assert_foo_error(e: FooError) {
+    match e {
+        FooError::Timeout => (),
+        FooError::Tuply(_, _) => (),
+        FooError::Structy { u: _ } => (),
+    }
+}
+
+fn main() {}
@@ -0,0 +1,39 @@
+use errgo::errgo;
+
+mod errors {
+    pub type Result<T, E = Box<dyn std::error::Error>> = std::result::Result<T, E>;
+}
+
+type Fallible<T> = Result<T, std::io::Error>;
+
+#[errgo]
+fn qualified() -> errors::Result<(), QualifiedError> {
+    Err(err!(Bar))
+}
+
+#[errgo(alias = errors::Result)]
+fn defaulted() -> errors::Result<(), DefaultedError> {
+    Err(err!(Bar))
+}
+
+#[errgo(error = SingleArgError, derive(Debug, thiserror::Error))]
+fn single_arg() -> errors::Result<()> {
+    Err(err!(
+        #[error("bar")]
+        Bar
+    ))?
+}
+
+#[errgo(error = IoWrappingError)]
+fn io_wrapping() -> Fallible<usize> {
+    let _ = err!(Bar(usize = 1));
+    Ok(1)
+}
+
+fn assert_errors(_: QualifiedError, _: DefaultedError, e: SingleArgError, _: IoWrappingError) {
+    match e {
+        SingleArgError::Bar => (),
+    }
+}
+
+fn main() {}
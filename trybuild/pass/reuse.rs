@@ -0,0 +1,55 @@
+use errgo::errgo;
+use std::path::PathBuf;
+
+#[errgo(derive(Debug))]
+fn foo(p: PathBuf, u: usize) -> Result<(), FooError> {
+    if u == 0 {
+        // used before its declaration
+        return Err(err!(NotFound { path: PathBuf::from("/") }));
+    }
+    if u == 1 {
+        return Err(err!(NotFound { path: p }));
+    }
+    if u == 2 {
+        return Err(err!(Tuply(u, 'a')));
+    }
+    if u == 3 {
+        return Err(err!(Tuply(usize = u, char = 'b')));
+    }
+    Err(err!(NotFound {
+        path: PathBuf = PathBuf::new()
+    }))
+}
+
+fn assert_foo_error(e: FooError) {
+    match e {
+        FooError::NotFound { path } => {
+            let _: PathBuf = path;
+        }
+        FooError::Tuply(u, c) => {
+            let _: usize = u;
+            let _: char = c;
+        }
+    }
+}
+
+#[errgo(derive(Debug))]
+fn bar(p: &std::path::Path, items: &[u8]) -> Result<(), BarError> {
+    if items.is_empty() {
+        // a local variable rather than a parameter
+        let path = p.to_path_buf();
+        return Err(err!(Missing { path }));
+    }
+    if items.len() > 8 {
+        let local = items.len();
+        return Err(err!(TooLong { len = local }));
+    }
+    if items.len() > 4 {
+        return Err(err!(TooLong { len: usize = items.len() }));
+    }
+    Err(err!(Missing {
+        path: PathBuf = p.into()
+    }))
+}
+
+fn main() {}
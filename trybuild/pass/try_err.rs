@@ -0,0 +1,22 @@
+use errgo::errgo;
+use std::{io, num::ParseIntError};
+
+fn op() -> Result<(), io::Error> {
+    Ok(())
+}
+
+#[errgo(derive(Debug))]
+fn wrap(s: &str) -> Result<usize, FooError> {
+    try_err!(Io(io::Error), op())?;
+    let parsed = try_err!(Parse(ParseIntError), s.parse::<usize>())?;
+    op()?;
+    try_err!(Io, op())?;
+    let closure = || try_err!(Io, op());
+    closure()?;
+    Ok(parsed)
+}
+
+fn main() {
+    let _: FooError = io::Error::other("").into();
+    let _ = wrap("1");
+}
@@ -0,0 +1,62 @@
+use errgo::errgo;
+use std::{io, ops::ControlFlow, task::Poll};
+
+fn op() -> Result<(), io::Error> {
+    Ok(())
+}
+
+#[errgo(derive(Debug))]
+fn poll(ready: bool) -> Poll<Result<(), PollError>> {
+    match ready {
+        true => Poll::Ready(Err(err!(Failed))),
+        false => Poll::Pending,
+    }
+}
+
+#[errgo(derive(Debug))]
+fn next(done: bool) -> Option<Result<u8, NextError>> {
+    match done {
+        true => None,
+        false => Some(Err(err!(Failed { index: usize = 0 }))),
+    }
+}
+
+#[errgo(derive(Debug))]
+fn visit(n: u8) -> ControlFlow<Result<(), VisitError>> {
+    match n {
+        0 => ControlFlow::Break(Err(err!(Empty))),
+        _ => ControlFlow::Continue(()),
+    }
+}
+
+#[errgo(derive(Debug))]
+fn boxed(fail: bool) -> Result<(), Box<BoxedError>> {
+    try_err!(Io(io::Error), op())?;
+    if fail {
+        return Err(err!(Failed([u8; 64] = [0; 64])));
+    }
+    Ok(())
+}
+
+#[errgo(error = ModError, derive(Debug))]
+mod module {
+    pub fn boxed() -> Result<(), Box<ModError>> {
+        Err(err!(Boxed))
+    }
+    pub fn unboxed() -> Result<(), ModError> {
+        Err(err!(Unboxed))
+    }
+}
+
+fn main() {
+    assert!(matches!(poll(true), Poll::Ready(Err(PollError::Failed))));
+    assert!(matches!(next(false), Some(Err(NextError::Failed { index: 0 }))));
+    assert!(matches!(
+        visit(0),
+        ControlFlow::Break(Err(VisitError::Empty))
+    ));
+    let error: Box<BoxedError> = boxed(true).unwrap_err();
+    assert!(matches!(*error, BoxedError::Failed(_)));
+    assert!(matches!(*module::boxed().unwrap_err(), module::ModError::Boxed));
+    assert!(matches!(module::unboxed().unwrap_err(), module::ModError::Unboxed));
+}